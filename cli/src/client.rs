@@ -0,0 +1,75 @@
+use anyhow::{bail, Context, Result};
+use reqwest::{Method, RequestBuilder, Response};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// The REST client shared by all commands. It prefixes paths with the server URL and sends the auth token.
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    http: reqwest::Client,
+    base_url: String,
+    auth_token: Option<String>,
+}
+
+/// The error body of the server.
+#[derive(Debug, Deserialize)]
+struct ErrorMessage {
+    message: String,
+}
+
+impl ApiClient {
+    pub fn new(base_url: &str, auth_token: Option<String>, proxy: Option<&str>) -> Result<Self> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy) = proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy).with_context(|| format!("Invalid proxy `{}`", proxy))?);
+        }
+        Ok(ApiClient {
+            http: builder.build()?,
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            auth_token,
+        })
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// Build a request to `path`, with the auth token if given.
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self.http.request(method, self.url(path));
+        match &self.auth_token {
+            Some(auth_token) => request.bearer_auth(auth_token),
+            None => request,
+        }
+    }
+
+    /// Send the request. Responses other than 2xx are turned into errors with the message from the server.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let url = response.url().to_string();
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<ErrorMessage>(&body)
+            .map(|e| e.message)
+            .unwrap_or(body);
+        bail!("Request to `{}` failed with {}: {}", url, status, message)
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let response = self.send(self.request(Method::GET, path)).await?;
+        response
+            .json()
+            .await
+            .with_context(|| format!("Unexpected response from `{}`", self.url(path)))
+    }
+
+    pub async fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> Result<T> {
+        let response = self.send(self.request(Method::POST, path).json(body)).await?;
+        response
+            .json()
+            .await
+            .with_context(|| format!("Unexpected response from `{}`", self.url(path)))
+    }
+}
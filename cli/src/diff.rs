@@ -0,0 +1,111 @@
+use crate::model::{Course, CourseGroup, NewCourse};
+use crate::output::Table;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Write},
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NewGroup {
+    pub code: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangedCourse {
+    pub code_id: String,
+    pub name: String,
+    pub old_teachers: String,
+    pub new_teachers: String,
+    pub old_credit: f64,
+    pub new_credit: f64,
+}
+
+/// What importing the file would change on the server for the given semester.
+#[derive(Debug, Default, Serialize)]
+pub struct CourseDiff {
+    /// Course groups that do not exist yet and would be created.
+    pub new_groups: Vec<NewGroup>,
+    /// Courses that do not exist in the semester yet and would be created.
+    pub new_courses: Vec<NewCourse>,
+    /// Courses that exist in the semester, but whose teachers or credit differ from the file.
+    pub changed_courses: Vec<ChangedCourse>,
+    /// Courses that exist in the semester, but are missing from the file.
+    pub missing_courses: Vec<Course>,
+}
+
+impl CourseDiff {
+    pub fn new(groups: Vec<CourseGroup>, courses: &[NewCourse], year: i32, semester: i32) -> Self {
+        let group_codes: HashSet<&str> = groups.iter().map(|group| group.code.as_str()).collect();
+        let remote: HashMap<&str, &Course> = groups
+            .iter()
+            .flat_map(|group| group.course_list.iter())
+            .filter(|course| course.year == year && course.semester == semester)
+            .map(|course| (course.code_id.as_str(), course))
+            .collect();
+
+        let mut diff = CourseDiff::default();
+        let mut seen_new_groups = HashSet::new();
+        for course in courses {
+            if !group_codes.contains(course.code.as_str()) && seen_new_groups.insert(course.code.as_str()) {
+                diff.new_groups.push(NewGroup {
+                    code: course.code.clone(),
+                    name: course.name.clone(),
+                });
+            }
+            match remote.get(course.code_id.as_str()) {
+                None => diff.new_courses.push(course.clone()),
+                Some(old) => {
+                    if old.teachers != course.teachers || old.credit != course.credit {
+                        diff.changed_courses.push(ChangedCourse {
+                            code_id: course.code_id.clone(),
+                            name: course.name.clone(),
+                            old_teachers: old.teachers.clone(),
+                            new_teachers: course.teachers.clone(),
+                            old_credit: old.credit,
+                            new_credit: course.credit,
+                        });
+                    }
+                }
+            }
+        }
+
+        let local: HashSet<&str> = courses.iter().map(|course| course.code_id.as_str()).collect();
+        diff.missing_courses = remote
+            .values()
+            .filter(|course| !local.contains(course.code_id.as_str()))
+            .map(|course| (*course).clone())
+            .collect();
+        diff.missing_courses.sort_by(|a, b| a.code_id.cmp(&b.code_id));
+        diff
+    }
+}
+
+impl Table for CourseDiff {
+    fn write_table(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "New groups ({}):", self.new_groups.len())?;
+        for group in &self.new_groups {
+            writeln!(out, "  {:<20} {}", group.code, group.name)?;
+        }
+        writeln!(out, "New courses ({}):", self.new_courses.len())?;
+        for course in &self.new_courses {
+            writeln!(out, "  {:<20} {:<30} {:<6} {}", course.code_id, course.name, course.credit, course.teachers)?;
+        }
+        writeln!(out, "Changed courses ({}):", self.changed_courses.len())?;
+        for course in &self.changed_courses {
+            writeln!(out, "  {:<20} {}", course.code_id, course.name)?;
+            if course.old_teachers != course.new_teachers {
+                writeln!(out, "  {:<20} teachers: {} -> {}", "", course.old_teachers, course.new_teachers)?;
+            }
+            if course.old_credit != course.new_credit {
+                writeln!(out, "  {:<20} credit: {} -> {}", "", course.old_credit, course.new_credit)?;
+            }
+        }
+        writeln!(out, "Missing from the file ({}):", self.missing_courses.len())?;
+        for course in &self.missing_courses {
+            writeln!(out, "  {:<20} {:<30} {:<6} {}", course.code_id, course.name, course.credit, course.teachers)?;
+        }
+        Ok(())
+    }
+}
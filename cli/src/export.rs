@@ -0,0 +1,200 @@
+use crate::client::ApiClient;
+use crate::model::{CourseGroup, CourseGroupWithReviews};
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::{fs::File, io::Write};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ExportKind {
+    Courses,
+    Reviews,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+/// Export courses or reviews from the server.
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// What to export
+    #[arg(value_enum)]
+    kind: ExportKind,
+
+    /// The format of the exported file
+    #[arg(short, long, value_enum, default_value_t = ExportFormat::Json)]
+    format: ExportFormat,
+
+    /// The file to write to. Defaults to the standard output.
+    #[arg(short = 'O', long)]
+    out: Option<String>,
+
+    /// Only export the courses (or reviews of the courses) in this year
+    #[arg(short, long)]
+    year: Option<i32>,
+
+    /// Only export the courses (or reviews of the courses) in this semester
+    #[arg(short, long)]
+    semester: Option<i32>,
+}
+
+/// A course, flattened with its group so that it fits in a CSV row.
+#[derive(Debug, Serialize)]
+pub struct CourseRow {
+    pub group_id: i32,
+    pub group_code: String,
+    pub id: i32,
+    pub name: String,
+    pub code: String,
+    pub code_id: String,
+    pub credit: f64,
+    pub department: String,
+    pub campus_name: String,
+    pub teachers: String,
+    pub max_student: i32,
+    pub week_hour: i32,
+    pub year: i32,
+    pub semester: i32,
+}
+
+/// A review, flattened with its course so that it fits in a CSV row.
+#[derive(Debug, Serialize)]
+pub struct ReviewRow {
+    pub course_id: i32,
+    pub code_id: String,
+    pub course_name: String,
+    pub year: i32,
+    pub semester: i32,
+    pub review_id: i32,
+    pub title: String,
+    pub content: String,
+    /// Only exported for admin.
+    pub reviewer_id: Option<i32>,
+    pub reviewer_pseudonym: String,
+    pub time_created: String,
+    pub time_updated: String,
+    pub remark: i32,
+    /// The rank object, encoded as JSON.
+    pub rank: String,
+}
+
+async fn export_courses(client: &ApiClient, args: &ExportArgs) -> Result<Vec<CourseRow>> {
+    let groups: Vec<CourseGroup> = client.get("/courses").await?;
+    let (year, semester) = (args.year, args.semester);
+    Ok(groups
+        .into_iter()
+        .flat_map(|group| {
+            let (group_id, group_code) = (group.id, group.code);
+            group
+                .course_list
+                .into_iter()
+                .filter(move |course| course.is_in(year, semester))
+                .map(move |course| CourseRow {
+                    group_id,
+                    group_code: group_code.clone(),
+                    id: course.id,
+                    name: course.name,
+                    code: course.code,
+                    code_id: course.code_id,
+                    credit: course.credit,
+                    department: course.department,
+                    campus_name: course.campus_name,
+                    teachers: course.teachers,
+                    max_student: course.max_student,
+                    week_hour: course.week_hour,
+                    year: course.year,
+                    semester: course.semester,
+                })
+        })
+        .collect())
+}
+
+/// `GET /courses` does not include reviews, so every course group with matching courses is fetched one by one.
+async fn export_reviews(client: &ApiClient, args: &ExportArgs) -> Result<Vec<ReviewRow>> {
+    let groups: Vec<CourseGroup> = client.get("/courses").await?;
+    let group_ids: Vec<i32> = groups
+        .iter()
+        .filter(|group| group.course_list.iter().any(|course| course.is_in(args.year, args.semester)))
+        .map(|group| group.id)
+        .collect();
+
+    let pb = ProgressBar::new(group_ids.len() as u64);
+    let mut rows = vec![];
+    for group_id in group_ids {
+        let group: CourseGroupWithReviews = client
+            .get(&format!("/group/{}", group_id))
+            .await
+            .with_context(|| format!("Failed to fetch course group {}", group_id))?;
+        for course in group.course_list {
+            let course_info = course.course;
+            if !course_info.is_in(args.year, args.semester) {
+                continue;
+            }
+            for review in course.review_list {
+                rows.push(ReviewRow {
+                    course_id: course_info.id,
+                    code_id: course_info.code_id.clone(),
+                    course_name: course_info.name.clone(),
+                    year: course_info.year,
+                    semester: course_info.semester,
+                    review_id: review.id,
+                    title: review.title,
+                    content: review.content,
+                    reviewer_id: review.reviewer_id,
+                    reviewer_pseudonym: review.reviewer_pseudonym,
+                    time_created: review.time_created,
+                    time_updated: review.time_updated,
+                    remark: review.remark,
+                    rank: review.rank.to_string(),
+                });
+            }
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+    Ok(rows)
+}
+
+fn write_rows<T: Serialize>(rows: &[T], format: ExportFormat, out: &mut dyn Write) -> Result<()> {
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, rows)?;
+            writeln!(out)?;
+        }
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            for row in rows {
+                writer.serialize(row)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn run(client: &ApiClient, args: ExportArgs, out: &mut dyn Write) -> Result<()> {
+    let mut file;
+    let out: &mut dyn Write = match &args.out {
+        Some(path) => {
+            file = File::create(path).with_context(|| format!("Failed to create `{}`", path))?;
+            &mut file
+        }
+        None => out,
+    };
+    match args.kind {
+        ExportKind::Courses => {
+            let rows = export_courses(client, &args).await?;
+            eprintln!("Exported {} courses", rows.len());
+            write_rows(&rows, args.format, out)
+        }
+        ExportKind::Reviews => {
+            let rows = export_reviews(client, &args).await?;
+            eprintln!("Exported {} reviews", rows.len());
+            write_rows(&rows, args.format, out)
+        }
+    }
+}
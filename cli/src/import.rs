@@ -0,0 +1,396 @@
+use crate::client::ApiClient;
+use crate::diff::CourseDiff;
+use crate::input::{self, into_new_course, raw_course_no, ColumnMapping, InputFormat};
+use crate::model::{CourseGroup, NewCourse, Semester};
+use crate::output::{write_output, OutputFormat};
+use crate::TERMINATE;
+use anyhow::{bail, Context, Result};
+use clap::Args;
+use either::Either;
+use indicatif::ProgressBar;
+use reqwest::Method;
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    fs::{read_to_string, File, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+    sync::atomic::Ordering,
+    time::Duration,
+};
+use tokio::{task::JoinSet, time::sleep};
+
+/// Import courses from a JSON, CSV or Excel file.
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// The file to import, in JSON, CSV or Excel (XLSX) format
+    #[arg(short, long, visible_alias = "file")]
+    json_file: String,
+
+    /// The format of the file to import.
+    ///
+    /// Detected from the file extension if not given.
+    #[arg(short, long, value_enum)]
+    format: Option<InputFormat>,
+
+    /// Map a field to a column header of a CSV or XLSX file, as `<field>=<header>`. Can be repeated.
+    ///
+    /// Fields are `name`, `no`, `teachers`, `credits`, `department`, `campus_name`, `max_student` and `week_hour`.
+    /// E.g. `--column no=课程序号 --column credits=学分`.
+    #[arg(long = "column", value_name = "FIELD=HEADER")]
+    columns: Vec<String>,
+
+    /// A JSON file mapping fields to column headers, e.g. `{"no": "课程序号", "credits": "学分"}`.
+    ///
+    /// `--column` takes precedence over the mapping file.
+    #[arg(long)]
+    mapping_file: Option<String>,
+
+    /// Which year to import
+    /// 
+    /// E.g. `2021` means 2021-2022 academic year.
+    #[arg(short, long)]
+    year: i32,
+
+    /// Which semester to import
+    /// 
+    /// E.g. `1` means the autumn semester, `2` means the (next year's) winter holiday, `3` means the (next year's) spring semester, `4` means the (next year's) summer holiday.
+    /// The semester must be registered on the server, see `GET /semesters`.
+    #[arg(short, long)]
+    semester: i32,
+
+    /// The checkpoint file recording the `code_id`s of imported courses.
+    ///
+    /// Defaults to `<json_file>.checkpoint`.
+    #[arg(long)]
+    checkpoint: Option<String>,
+
+    /// Skip the courses recorded in the checkpoint file of a previous run.
+    #[arg(long)]
+    resume: bool,
+
+    /// The JSON report listing every failed course of this run.
+    ///
+    /// Defaults to `<json_file>.report.json`.
+    #[arg(long)]
+    report: Option<String>,
+
+    /// Only print what would change on the server, without importing anything.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// How many requests may be in flight at the same time.
+    #[arg(long, default_value_t = 1)]
+    concurrency: usize,
+
+    /// How many times to retry a course on network errors and 5xx responses.
+    ///
+    /// Before retrying, the server is asked whether the course was created anyway.
+    #[arg(long, default_value_t = 3)]
+    retries: u32,
+}
+
+/// Records the `code_id` of every imported course, one per line, so that an interrupted run can be resumed.
+struct Checkpoint {
+    imported: HashSet<String>,
+    writer: BufWriter<File>,
+}
+
+impl Checkpoint {
+    fn open(path: &str, resume: bool) -> Result<Self> {
+        let imported = if resume && Path::new(path).exists() {
+            read_to_string(path)
+                .with_context(|| format!("Failed to read checkpoint file `{}`", path))?
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(str::to_owned)
+                .collect()
+        } else {
+            HashSet::new()
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resume)
+            .truncate(!resume)
+            .open(path)
+            .with_context(|| format!("Failed to open checkpoint file `{}`", path))?;
+        Ok(Checkpoint {
+            imported,
+            writer: BufWriter::new(file),
+        })
+    }
+
+    fn contains(&self, code_id: &str) -> bool {
+        self.imported.contains(code_id)
+    }
+
+    fn record(&mut self, code_id: &str) -> Result<()> {
+        writeln!(self.writer, "{}", code_id)?;
+        // Flush immediately so that nothing is lost if the program crashes.
+        self.writer.flush()?;
+        self.imported.insert(code_id.to_owned());
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct FailedCourse {
+    course: NewCourse,
+    /// `None` if the request failed before receiving a response.
+    status: Option<u16>,
+    body: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Report {
+    total: usize,
+    imported: usize,
+    skipped: usize,
+    interrupted: bool,
+    failed: Vec<FailedCourse>,
+}
+
+impl Report {
+    fn write(&self, path: &str) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write report to `{}`", path))
+    }
+}
+
+/// Whether the course exists on the server, e.g. created by an earlier attempt whose response was lost.
+async fn course_exists(client: &ApiClient, course: &NewCourse) -> Result<bool> {
+    let path = format!("/courses?year={}&semester={}", course.year, course.semester);
+    let groups: Vec<CourseGroup> = client.get(&path).await?;
+    Ok(groups
+        .iter()
+        .flat_map(|group| &group.course_list)
+        .any(|existing| existing.code_id == course.code_id))
+}
+
+/// POST a course, retrying with exponential backoff on network errors and 5xx responses.
+///
+/// `POST /courses` is not idempotent: the course may have been created even if the request failed.
+/// Unless the request never reached the server, the course is looked up before each retry,
+/// so that it is never created twice.
+async fn import_course(
+    client: ApiClient,
+    course: NewCourse,
+    retries: u32,
+) -> (NewCourse, Result<(), FailedCourse>) {
+    let mut attempt = 0;
+    loop {
+        let resq = client
+            .request(Method::POST, "/courses")
+            .json(&course)
+            .send()
+            .await;
+        let retryable = match &resq {
+            Ok(resq) => resq.status().is_server_error(),
+            Err(_) => true,
+        };
+        if retryable && attempt < retries && !TERMINATE.load(Ordering::SeqCst) {
+            sleep(RETRY_BASE_DELAY * 2u32.pow(attempt)).await;
+            attempt += 1;
+            let reached_server = !matches!(&resq, Err(e) if e.is_connect());
+            if !reached_server {
+                continue;
+            }
+            match course_exists(&client, &course).await {
+                Ok(true) => return (course, Ok(())),
+                Ok(false) => continue,
+                // Give up rather than risk a duplicate; the failure is kept in the report.
+                Err(_) => {}
+            }
+        }
+        let result = match resq {
+            Ok(resq) if resq.status().is_success() => Ok(()),
+            Ok(resq) => {
+                let status = resq.status().as_u16();
+                Err(FailedCourse {
+                    course: course.clone(),
+                    status: Some(status),
+                    body: resq.text().await.unwrap_or_default(),
+                })
+            }
+            Err(e) => Err(FailedCourse {
+                course: course.clone(),
+                status: None,
+                body: e.to_string(),
+            }),
+        };
+        return (course, result);
+    }
+}
+
+fn record_outcome(
+    (course, result): (NewCourse, Result<(), FailedCourse>),
+    pb: &ProgressBar,
+    checkpoint: &mut Checkpoint,
+    report: &mut Report,
+) -> Result<()> {
+    pb.inc(1);
+    match result {
+        Ok(()) => {
+            checkpoint.record(&course.code_id)?;
+            report.imported += 1;
+        }
+        Err(failed) => {
+            pb.println(format!(
+                "Failed to import course `{:?}`: {}",
+                failed.course, failed.body
+            ));
+            report.failed.push(failed);
+        }
+    }
+    Ok(())
+}
+
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Make sure the semester is registered on the server, and return its name.
+async fn check_semester(client: &ApiClient, year: i32, semester: i32) -> Result<String> {
+    let semesters: Vec<Semester> = client
+        .get("/semesters")
+        .await
+        .context("Failed to fetch semesters from the server")?;
+    if let Some(name) = semesters
+        .iter()
+        .find(|s| s.year == year && s.semester == semester)
+        .and_then(|s| s.name.clone())
+    {
+        return Ok(name);
+    }
+    let registered: Vec<String> = semesters
+        .iter()
+        .filter_map(|s| s.name.as_ref().map(|name| format!("{}-{} ({})", s.year, s.semester, name)))
+        .collect();
+    bail!(
+        "Semester {} of year {} is not registered on the server, register it with `semesters add`. Registered semesters: {}",
+        semester,
+        year,
+        if registered.is_empty() { "none".to_owned() } else { registered.join(", ") }
+    )
+}
+
+pub async fn run(client: &ApiClient, args: ImportArgs, output: OutputFormat, out: &mut dyn Write) -> Result<()> {
+    let mapping = ColumnMapping::load(args.mapping_file.as_deref(), &args.columns)?;
+
+    let semester_name = check_semester(client, args.year, args.semester).await?;
+    eprintln!("Importing into semester `{}`", semester_name);
+
+    eprintln!("Reading courses from `{}`", args.json_file);
+    let raw_courses = input::read_courses(&args.json_file, args.format, &mapping)
+        .with_context(|| format!("Failed to read courses from `{}`", args.json_file))?;
+
+    let course_num = match &raw_courses {
+        Either::Left(raw_courses) => raw_courses.len(),
+        Either::Right(raw_jwfw_courses) => raw_jwfw_courses.len(),
+    };
+    eprintln!("Found {} courses", course_num);
+
+    let course_iter = raw_courses
+        .map_either(
+            |a| a.into_iter().map(Either::Left),
+            |b| b.into_iter().map(Either::Right),
+        )
+        .into_iter();
+
+    eprintln!("Fetching existing courses from `{}`", client.url("/courses"));
+    let groups: Vec<CourseGroup> = client
+        .get("/courses")
+        .await
+        .context("Failed to fetch existing courses from the server")?;
+
+    if args.dry_run {
+        let new_courses: Vec<NewCourse> = course_iter
+            .filter(|raw_course| raw_course_no(raw_course).len() > 3)
+            .map(|raw_course| into_new_course(raw_course, args.year, args.semester))
+            .collect();
+        let diff = CourseDiff::new(groups, &new_courses, args.year, args.semester);
+        return write_output(&diff, output, out);
+    }
+
+    let existing: HashSet<String> = groups
+        .into_iter()
+        .flat_map(|group| group.course_list)
+        .filter(|course| course.year == args.year && course.semester == args.semester)
+        .map(|course| course.code_id)
+        .collect();
+
+    let pb = ProgressBar::new(course_num as u64);
+
+    let checkpoint_path = args
+        .checkpoint
+        .unwrap_or_else(|| format!("{}.checkpoint", args.json_file));
+    let mut checkpoint = Checkpoint::open(&checkpoint_path, args.resume)?;
+
+    let mut report = Report {
+        total: course_num,
+        ..Default::default()
+    };
+
+    let mut in_flight = JoinSet::new();
+    for raw_course in course_iter {
+        if TERMINATE.load(Ordering::SeqCst) {
+            let mut input = String::new();
+            pb.suspend(|| {
+                print!("Do you want to stop the program? (y/N) ");
+                let _ = std::io::stdout().flush();
+                std::io::stdin()
+                    .read_line(&mut input)
+                    .expect("Failed to read input");
+            });
+            if input.trim().to_lowercase() == "y" {
+                report.interrupted = true;
+                break;
+            } else {
+                TERMINATE.store(false, Ordering::SeqCst);
+            }
+        }
+
+        let no = raw_course_no(&raw_course).to_owned();
+
+        let len = no.len();
+        if len <= 3 {
+            pb.println(format!("The no of course `{:?}` is too short", raw_course));
+            pb.inc(1);
+            report.skipped += 1;
+            continue;
+        }
+
+        if checkpoint.contains(&no) || existing.contains(&no) {
+            pb.inc(1);
+            report.skipped += 1;
+            continue;
+        }
+
+        // Wait for a free slot before sending the next request.
+        while in_flight.len() >= args.concurrency.max(1) {
+            if let Some(outcome) = in_flight.join_next().await {
+                record_outcome(outcome?, &pb, &mut checkpoint, &mut report)?;
+            }
+        }
+
+        let new_course = into_new_course(raw_course, args.year, args.semester);
+        in_flight.spawn(import_course(client.clone(), new_course, args.retries));
+    }
+    // Requests already sent are always waited for, even if interrupted.
+    while let Some(outcome) = in_flight.join_next().await {
+        record_outcome(outcome?, &pb, &mut checkpoint, &mut report)?;
+    }
+    pb.finish();
+    let report_path = args
+        .report
+        .unwrap_or_else(|| format!("{}.report.json", args.json_file));
+    report.write(&report_path)?;
+    writeln!(out, "Report is written to `{}`", report_path)?;
+    if report.interrupted {
+        writeln!(out, "Interrupted. Run again with `--resume` to continue from the checkpoint `{}`.", checkpoint_path)?;
+    } else if report.failed.is_empty() {
+        writeln!(out, "Congratulations! All courses have been imported successfully!")?;
+    } else {
+        writeln!(out, "{} courses failed to import. See the report for details.", report.failed.len())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,348 @@
+use crate::model::NewCourse;
+use anyhow::{anyhow, bail, Context, Result};
+use calamine::{open_workbook_auto, Data, Range, Reader};
+use clap::ValueEnum;
+use either::Either;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs::read_to_string, path::Path};
+
+#[derive(Debug, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct RawCourse {
+    pub name: String,
+    pub no: String,
+    pub teachDepartName: String,
+    pub teachers: String,
+    pub credits: f64,
+    pub maxStudent: i32,
+    pub campusName: String,
+    pub weekHour: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RawJwfwCourse {
+    pub name: String,
+    pub no: String,
+    pub teachers: String,
+    pub credits: f64,
+    pub department: String,
+}
+
+impl RawCourse {
+    pub fn into_new_course(self, year: i32, semester: i32) -> NewCourse {
+        let code = self.no.rfind('.').map(|pos| &self.no[0..pos]).unwrap_or(&self.no);
+        NewCourse {
+            name: self.name,
+            code: code.to_string(),
+            department: self.teachDepartName,
+            teachers: self.teachers,
+            credit: self.credits,
+            code_id: self.no,
+            campus_name: self.campusName,
+            max_student: self.maxStudent,
+            week_hour: self.weekHour,
+            year,
+            semester,
+        }
+    }
+}
+
+impl RawJwfwCourse {
+    pub fn into_new_course(self, year: i32, semester: i32) -> NewCourse {
+        let code = self.no.rfind('.').map(|pos| &self.no[0..pos]).unwrap_or(&self.no);
+        NewCourse {
+            name: self.name,
+            code: code.to_string(),
+            department: self.department,
+            teachers: self.teachers,
+            credit: self.credits,
+            code_id: self.no,
+            campus_name: Default::default(),
+            max_student: Default::default(),
+            week_hour: Default::default(),
+            year,
+            semester,
+        }
+    }
+}
+
+pub fn raw_course_no(raw_course: &Either<RawCourse, RawJwfwCourse>) -> &str {
+    match raw_course {
+        Either::Left(raw_course) => &raw_course.no,
+        Either::Right(raw_course) => &raw_course.no,
+    }
+}
+
+pub fn into_new_course(raw_course: Either<RawCourse, RawJwfwCourse>, year: i32, semester: i32) -> NewCourse {
+    match raw_course {
+        Either::Left(raw_course) => raw_course.into_new_course(year, semester),
+        Either::Right(raw_course) => raw_course.into_new_course(year, semester),
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    Json,
+    Csv,
+    Xlsx,
+}
+
+impl InputFormat {
+    /// Guess the format from the file extension.
+    pub fn detect(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "json" => Some(InputFormat::Json),
+            "csv" => Some(InputFormat::Csv),
+            "xlsx" | "xls" | "xlsm" | "ods" => Some(InputFormat::Xlsx),
+            _ => None,
+        }
+    }
+}
+
+/// Which column header of a CSV or XLSX file holds each field.
+///
+/// `campus_name`, `max_student` and `week_hour` are optional: if the column is absent, they are left empty.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnMapping {
+    pub name: String,
+    pub no: String,
+    pub teachers: String,
+    pub credits: String,
+    pub department: String,
+    pub campus_name: String,
+    pub max_student: String,
+    pub week_hour: String,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        ColumnMapping {
+            name: "name".to_string(),
+            no: "no".to_string(),
+            teachers: "teachers".to_string(),
+            credits: "credits".to_string(),
+            department: "department".to_string(),
+            campus_name: "campusName".to_string(),
+            max_student: "maxStudent".to_string(),
+            week_hour: "weekHour".to_string(),
+        }
+    }
+}
+
+impl ColumnMapping {
+    /// Build the mapping from an optional JSON mapping file, then apply `<field>=<header>` overrides.
+    pub fn load(mapping_file: Option<&str>, overrides: &[String]) -> Result<Self> {
+        let mut mapping = match mapping_file {
+            Some(path) => {
+                let content = read_to_string(path)
+                    .with_context(|| format!("Failed to read column mapping from `{}`", path))?;
+                serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse column mapping from `{}`", path))?
+            }
+            None => ColumnMapping::default(),
+        };
+        for column in overrides {
+            let (field, header) = column
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid column mapping `{}`, expected `<field>=<header>`", column))?;
+            let header = header.to_string();
+            match field {
+                "name" => mapping.name = header,
+                "no" => mapping.no = header,
+                "teachers" => mapping.teachers = header,
+                "credits" => mapping.credits = header,
+                "department" => mapping.department = header,
+                "campus_name" => mapping.campus_name = header,
+                "max_student" => mapping.max_student = header,
+                "week_hour" => mapping.week_hour = header,
+                _ => bail!("Unknown field `{}` in column mapping `{}`", field, column),
+            }
+        }
+        Ok(mapping)
+    }
+}
+
+/// The index of each mapped column in the header row.
+struct ColumnIndex {
+    name: usize,
+    no: usize,
+    teachers: usize,
+    credits: usize,
+    department: usize,
+    campus_name: Option<usize>,
+    max_student: Option<usize>,
+    week_hour: Option<usize>,
+}
+
+impl ColumnIndex {
+    fn new(header: &[String], mapping: &ColumnMapping) -> Result<Self> {
+        let positions: HashMap<&str, usize> = header
+            .iter()
+            .enumerate()
+            .map(|(i, h)| (h.trim(), i))
+            .collect();
+        let mut missing = vec![];
+        let mut required = |column: &str| {
+            positions.get(column).copied().unwrap_or_else(|| {
+                missing.push(column.to_string());
+                0
+            })
+        };
+        let index = ColumnIndex {
+            name: required(&mapping.name),
+            no: required(&mapping.no),
+            teachers: required(&mapping.teachers),
+            credits: required(&mapping.credits),
+            department: required(&mapping.department),
+            campus_name: positions.get(mapping.campus_name.as_str()).copied(),
+            max_student: positions.get(mapping.max_student.as_str()).copied(),
+            week_hour: positions.get(mapping.week_hour.as_str()).copied(),
+        };
+        if !missing.is_empty() {
+            bail!("Missing required columns in the header row: {}", missing.join(", "));
+        }
+        Ok(index)
+    }
+}
+
+/// A row of a CSV or XLSX file, with its row number in the file (counted from 1, the same as in a spreadsheet).
+pub(crate) type NumberedRow = (usize, Vec<String>);
+
+/// Convert the rows of a CSV or XLSX file into courses. The first row must be the header.
+///
+/// Errors refer to the row numbers in the file, so blank lines and leading empty rows are counted.
+pub(crate) fn parse_rows(rows: Vec<NumberedRow>, mapping: &ColumnMapping) -> Result<Vec<RawCourse>> {
+    let mut rows = rows.into_iter();
+    let (_, header) = rows.next().ok_or_else(|| anyhow!("The file is empty"))?;
+    let index = ColumnIndex::new(&header, mapping)?;
+
+    let mut courses = vec![];
+    let mut errors = vec![];
+    for (row_number, row) in rows {
+        if row.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+        let cell = |column: usize| row.get(column).map(|c| c.trim()).unwrap_or_default();
+        let optional_cell = |column: Option<usize>| column.map(cell).unwrap_or_default();
+        let mut row_errors = vec![];
+
+        let no = cell(index.no);
+        if no.is_empty() {
+            row_errors.push(format!("empty `{}`", mapping.no));
+        }
+        let credits = cell(index.credits).parse::<f64>().unwrap_or_else(|_| {
+            row_errors.push(format!("invalid `{}` `{}`", mapping.credits, cell(index.credits)));
+            0.0
+        });
+        let mut parse_int = |column: Option<usize>, header: &str| {
+            let value = optional_cell(column);
+            if value.is_empty() {
+                return 0;
+            }
+            // Spreadsheets may store integers as floats, e.g. `30.0`
+            value.parse::<f64>().map(|v| v as i32).unwrap_or_else(|_| {
+                row_errors.push(format!("invalid `{}` `{}`", header, value));
+                0
+            })
+        };
+        let max_student = parse_int(index.max_student, &mapping.max_student);
+        let week_hour = parse_int(index.week_hour, &mapping.week_hour);
+
+        if !row_errors.is_empty() {
+            errors.push(format!("row {}: {}", row_number, row_errors.join(", ")));
+            continue;
+        }
+        courses.push(RawCourse {
+            name: cell(index.name).to_string(),
+            no: no.to_string(),
+            teachDepartName: cell(index.department).to_string(),
+            teachers: cell(index.teachers).to_string(),
+            credits,
+            maxStudent: max_student,
+            campusName: optional_cell(index.campus_name).to_string(),
+            weekHour: week_hour,
+        });
+    }
+    if !errors.is_empty() {
+        bail!("Invalid rows:\n{}", errors.join("\n"));
+    }
+    Ok(courses)
+}
+
+pub(crate) fn read_csv(content: &str) -> Result<Vec<NumberedRow>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let mut rows = vec![];
+    for record in reader.records() {
+        let record = record.map_err(|e| match e.position() {
+            Some(position) => anyhow!("row {}: invalid CSV record: {}", position.line(), e),
+            None => anyhow!("invalid CSV record: {}", e),
+        })?;
+        let line = match record.position() {
+            // The reader skips blank lines, but the position of a record starts before the skipped lines.
+            Some(position) => {
+                let skipped = content[position.byte() as usize..]
+                    .chars()
+                    .take_while(|c| *c == '\r' || *c == '\n')
+                    .filter(|c| *c == '\n')
+                    .count();
+                position.line() as usize + skipped
+            }
+            None => 0,
+        };
+        rows.push((line, record.iter().map(str::to_owned).collect()));
+    }
+    Ok(rows)
+}
+
+/// The rows of a worksheet. The used range does not necessarily start at the first row.
+pub(crate) fn worksheet_rows(range: &Range<Data>) -> Vec<NumberedRow> {
+    let first_row = range.start().map_or(0, |(row, _)| row as usize);
+    range
+        .rows()
+        .enumerate()
+        .map(|(i, row)| (first_row + i + 1, row.iter().map(|cell| cell.to_string()).collect()))
+        .collect()
+}
+
+fn read_xlsx(path: &str) -> Result<Vec<NumberedRow>> {
+    let mut workbook = open_workbook_auto(path)?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| anyhow!("The workbook has no worksheet"))??;
+    Ok(worksheet_rows(&range))
+}
+
+/// Parse a JSON file, trying each known shape in turn.
+fn parse_json(raw_json: &str) -> Result<Either<Vec<RawCourse>, Vec<RawJwfwCourse>>> {
+    let raw_courses = serde_json::from_str::<Vec<RawCourse>>(raw_json);
+    let raw_jwfw_courses = serde_json::from_str::<Vec<RawJwfwCourse>>(raw_json);
+    match (raw_courses, raw_jwfw_courses) {
+        (Ok(raw_courses), _) => Ok(Either::Left(raw_courses)),
+        (_, Ok(raw_jwfw_courses)) => Ok(Either::Right(raw_jwfw_courses)),
+        (Err(e), Err(_)) => Err(e.into()),
+    }
+}
+
+/// Read the courses from a file. If `format` is `None`, it is detected from the file extension.
+pub fn read_courses(
+    path: &str,
+    format: Option<InputFormat>,
+    mapping: &ColumnMapping,
+) -> Result<Either<Vec<RawCourse>, Vec<RawJwfwCourse>>> {
+    let format = format
+        .or_else(|| InputFormat::detect(path))
+        .ok_or_else(|| anyhow!("Cannot detect the format of `{}`, please specify `--format`", path))?;
+    match format {
+        InputFormat::Json => {
+            let content = read_to_string(path)?;
+            parse_json(&content)
+        }
+        InputFormat::Csv => Ok(Either::Left(parse_rows(read_csv(&read_to_string(path)?)?, mapping)?)),
+        InputFormat::Xlsx => Ok(Either::Left(parse_rows(read_xlsx(path)?, mapping)?)),
+    }
+}
@@ -1,132 +1,88 @@
-use anyhow::{Context, Result};
-use clap::Parser;
-use either::Either;
-use indicatif::ProgressBar;
-use serde::{Deserialize, Serialize};
+mod client;
+mod diff;
+mod export;
+mod import;
+mod input;
+mod manage;
+mod model;
+mod output;
+mod stats;
+mod tests;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use client::ApiClient;
+use export::ExportArgs;
+use import::ImportArgs;
+use manage::{AchievementsCommand, CacheCommand, GroupsCommand, SemestersCommand};
+use output::OutputFormat;
 use std::{
-    fs::read_to_string, io::Write, sync::atomic::{AtomicBool, Ordering}
+    io::Write,
+    sync::atomic::{AtomicBool, Ordering},
 };
 
-/// A command line tool for importing JSON files into curriculum database.
+/// A command line tool for administrating the curriculum board through its RESTful API.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
-struct Args {
-    /// The RESTful API URL of the server, NOT the database URL itself.
-    /// 
-    /// E.g. `http://localhost:8080/courses`
+struct Cli {
+    /// The base URL of the server, NOT the database URL itself.
+    ///
+    /// E.g. `http://localhost:11451`
     #[arg(short, long)]
-    db_url: String,
+    url: String,
 
     /// The auth token for the RESTful API, without `Bearer ` prefix.
     #[arg(short, long)]
-    auth_token: String,
-
-    /// The JSON file to import
-    #[arg(short, long)]
-    json_file: String,
-
-    /// Which year to import
-    /// 
-    /// E.g. `2021` means 2021-2022 academic year.
-    #[arg(short, long)]
-    year: i32,
-
-    /// Which semester to import
-    /// 
-    /// E.g. `1` means the autumn semester, `2` means the (next year's) winter holiday, `3` means the (next year's) spring semester, `4` means the (next year's) summer holiday.
-    #[arg(short, long)]
-    semester: i32,
+    auth_token: Option<String>,
 
     /// Proxy server URL, if needed
     #[arg(short, long)]
     proxy: Option<String>,
-}
 
-#[derive(Debug, Deserialize, Serialize)]
-struct RawCourse {
-    name: String,
-    no: String,
-    teachDepartName: String,
-    teachers: String,
-    credits: f64,
-    maxStudent: i32,
-    campusName: String,
-    weekHour: i32,
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-struct RawJwfwCourse {
-    name: String,
-    no: String,
-    teachers: String,
-    credits: f64,
-    department: String,
-}
+    /// The output format of the command results.
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
 
-#[derive(Debug, Deserialize, Serialize)]
-struct NewCourse {
-    campus_name: String,
-    code: String,
-    code_id: String,
-    credit: f64,
-    department: String,
-    max_student: i32,
-    name: String,
-    semester: i32,
-    teachers: String,
-    week_hour: i32,
-    year: i32,
+    #[command(subcommand)]
+    command: Command,
 }
 
-impl RawCourse {
-    fn into_new_course(self, year: i32, semester: i32) -> NewCourse {
-        let code = self.no.rfind('.').map(|pos| &self.no[0..pos]).unwrap_or(&self.no);
-        NewCourse {
-            name: self.name,
-            code: code.to_string(),
-            department: self.teachDepartName,
-            teachers: self.teachers,
-            credit: self.credits,
-            code_id: self.no,
-            campus_name: self.campusName,
-            max_student: self.maxStudent,
-            week_hour: self.weekHour,
-            year,
-            semester,
-        }
-    }
+#[derive(Subcommand, Debug)]
+enum Command {
+    Import(ImportArgs),
+    Export(ExportArgs),
+    /// Manage course groups.
+    #[command(subcommand)]
+    Groups(GroupsCommand),
+    /// Manage the course cache of the server.
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Manage achievements.
+    #[command(subcommand)]
+    Achievements(AchievementsCommand),
+    /// Manage semesters.
+    #[command(subcommand)]
+    Semesters(SemestersCommand),
+    /// Show an overview of the courses on the server.
+    Stats,
 }
 
-impl RawJwfwCourse {
-    fn into_new_course(self, year: i32, semester: i32) -> NewCourse {
-        let code = self.no.rfind('.').map(|pos| &self.no[0..pos]).unwrap_or(&self.no);
-        NewCourse {
-            name: self.name,
-            code: code.to_string(),
-            department: self.department,
-            teachers: self.teachers,
-            credit: self.credits,
-            code_id: self.no,
-            campus_name: Default::default(),
-            max_student: Default::default(),
-            week_hour: Default::default(),
-            year,
-            semester,
-        }
-    }
-}
+/// Set when Ctrl+C is pressed, so that long-running commands can stop gracefully.
+static TERMINATE: AtomicBool = AtomicBool::new(false);
 
-fn parse(raw_json: &String) -> Result<Either<Vec<RawCourse>, Vec<RawJwfwCourse>>> {
-    let raw_courses = serde_json::from_str::<Vec<RawCourse>>(&raw_json);
-    let raw_jwfw_courses = serde_json::from_str::<Vec<RawJwfwCourse>>(&raw_json);
-    match (raw_courses, raw_jwfw_courses) {
-        (Ok(raw_courses), _) => Ok(Either::Left(raw_courses)),
-        (_, Ok(raw_jwfw_courses)) => Ok(Either::Right(raw_jwfw_courses)),
-        (Err(e), Err(_)) => Err(e.into()),
+async fn run(cli: Cli, out: &mut dyn Write) -> Result<()> {
+    let client = ApiClient::new(&cli.url, cli.auth_token, cli.proxy.as_deref())?;
+    match cli.command {
+        Command::Import(args) => import::run(&client, args, cli.output, out).await,
+        Command::Export(args) => export::run(&client, args, out).await,
+        Command::Groups(command) => manage::groups(&client, command, cli.output, out).await,
+        Command::Cache(command) => manage::cache(&client, command, cli.output, out).await,
+        Command::Achievements(command) => manage::achievements(&client, command, cli.output, out).await,
+        Command::Semesters(command) => manage::semesters(&client, command, cli.output, out).await,
+        Command::Stats => stats::run(&client, cli.output, out).await,
     }
 }
 
-static TERMINATE: AtomicBool = AtomicBool::new(false);
 #[tokio::main]
 async fn main() -> Result<()> {
     let _ = ctrlc::set_handler(move || {
@@ -137,89 +93,6 @@ async fn main() -> Result<()> {
         println!("Error occurred when setting Ctrl+C handler: {}. You will be unable to stop the program during running gracefully!", e);
     });
 
-    let args = Args::parse();
-
-    println!("Reading JSON data from `{}`", args.json_file);
-    let content = read_to_string(&args.json_file)
-        .with_context(|| format!("Failed to read JSON file from `{}`", args.json_file))?;
-
-    let raw_courses = parse(&content)
-        .with_context(|| format!("Failed to parse JSON file from `{}`", args.json_file))?;
-
-    let course_num = match &raw_courses {
-        Either::Left(raw_courses) => raw_courses.len(),
-        Either::Right(raw_jwfw_courses) => raw_jwfw_courses.len(),
-    };
-    println!("Found {} courses", course_num);
-
-    let pb = ProgressBar::new(course_num as u64);
-
-    let course_iter = raw_courses
-        .map_either(
-            |a| a.into_iter().map(|v| Either::Left(v)),
-            |b| b.into_iter().map(|v| Either::Right(v)),
-        )
-        .into_iter();
-
-    let client = reqwest::Client::builder();
-    let client = if let Some(proxy) = args.proxy {
-        client
-            .proxy(reqwest::Proxy::all(proxy).expect("Failed to set proxy"))
-            .build()?
-    } else {
-        client.build()?
-    };
-
-    for raw_course in course_iter {
-        if TERMINATE.load(Ordering::SeqCst) {
-            let mut input = String::new();
-            print!("Do you want to stop the program? (y/N) ");
-            let _ = std::io::stdout().flush();
-            std::io::stdin()
-                .read_line(&mut input)
-                .expect("Failed to read input");
-            if input.trim().to_lowercase() == "y" {
-                return Ok(());
-            } else {
-                TERMINATE.store(false, Ordering::SeqCst);
-            }
-        }
-
-        let no = match &raw_course {
-            Either::Left(raw_course) => raw_course.no.to_owned(),
-            Either::Right(raw_course) => raw_course.no.to_owned(),
-        };
-
-        let len = no.len();
-        if len <= 3 {
-            println!("The no of course `{:?}` is too short", raw_course);
-            continue;
-        }
-
-        let new_course = match raw_course {
-            Either::Left(raw_course) => raw_course.into_new_course(args.year, args.semester),
-            Either::Right(raw_course) => raw_course.into_new_course(args.year, args.semester),
-        }; 
-
-        let resq = client
-            .post(&args.db_url)
-            .bearer_auth(&args.auth_token)
-            .json(&new_course)
-            .send()
-            .await?;
-        
-        pb.inc(1);
-        
-        if !resq.status().is_success() {
-            println!(
-                "Failed to import course `{:?}`: {}",
-                new_course,
-                resq.text().await?
-            );
-        }
-        
-    }
-    pb.finish();
-    println!("Congratulations! All courses have been imported successfully!");
-    Ok(())
+    let cli = Cli::parse();
+    run(cli, &mut std::io::stdout()).await
 }
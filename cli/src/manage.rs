@@ -0,0 +1,223 @@
+use crate::client::ApiClient;
+use crate::model::{Achievement, BackfillResult, CourseGroup, HashMessage, Semester};
+use crate::output::{write_output, OutputFormat, Table};
+use anyhow::{bail, Result};
+use clap::Subcommand;
+use reqwest::{Method, StatusCode};
+use serde::Serialize;
+use serde_json::json;
+use std::io::{self, Write};
+
+#[derive(Subcommand, Debug)]
+pub enum GroupsCommand {
+    /// Move all courses of the source groups into the target group, then delete the source groups.
+    Merge {
+        /// The id of the group to keep
+        target: i32,
+        /// The ids of the groups to merge into the target
+        #[arg(required = true)]
+        sources: Vec<i32>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommand {
+    /// Drop the course cache of the server, so that it is rebuilt from the database.
+    Refresh,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AchievementsCommand {
+    /// Grant an achievement to a user.
+    Grant {
+        user_id: i32,
+        achievement_id: i32,
+        /// When the achievement is obtained, e.g. `2023-09-01T00:00:00`. Defaults to now.
+        #[arg(long)]
+        obtain_date: Option<String>,
+    },
+    /// Re-evaluate the achievement rules of the server over all historical reviews,
+    /// granting achievements that users have earned but not received yet.
+    Backfill,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SemestersCommand {
+    /// List the registered semesters and the semesters that have courses, from the latest to the earliest.
+    List,
+    /// Register a semester, so that courses can be imported into it.
+    Add {
+        /// The first year of the academic year, e.g. 2021 for 2021-2022
+        year: i32,
+        /// The number of the semester in the academic year, e.g. 1 for autumn, 3 for spring
+        semester: i32,
+        /// The display name, e.g. `2021-2022 学年秋季学期`. Courses show no semester name if not given.
+        #[arg(long)]
+        name: Option<String>,
+        /// The first day of the semester, e.g. `2021-09-01`
+        #[arg(long)]
+        start_date: Option<String>,
+        /// The last day of the semester, e.g. `2022-01-15`
+        #[arg(long)]
+        end_date: Option<String>,
+        /// Make it the current semester, replacing the previous one.
+        #[arg(long)]
+        current: bool,
+    },
+}
+
+impl Table for CourseGroup {
+    fn write_table(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "Group {} {} {} ({} courses):", self.id, self.code, self.name, self.course_list.len())?;
+        for course in &self.course_list {
+            writeln!(
+                out,
+                "  {:<20} {:<30} {}-{} {}",
+                course.code_id, course.name, course.year, course.semester, course.teachers
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Table for Vec<Achievement> {
+    fn write_table(&self, out: &mut dyn Write) -> io::Result<()> {
+        for achievement in self {
+            writeln!(
+                out,
+                "{:<20} {:<15} {}",
+                achievement.name,
+                achievement.domain.as_deref().unwrap_or("-"),
+                achievement.obtain_date
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Table for BackfillResult {
+    fn write_table(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "Evaluated {} users, awarded {} achievements.",
+            self.users_evaluated, self.achievements_awarded
+        )
+    }
+}
+
+impl Table for Semester {
+    fn write_table(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(
+            out,
+            "{:>4}-{:<2} {:<30} {:>10} ~ {:<10} {:>5} courses{}",
+            self.year,
+            self.semester,
+            match (self.id, &self.name) {
+                (None, _) => "(not registered)",
+                (Some(_), Some(name)) => name,
+                (Some(_), None) => "-",
+            },
+            self.start_date.as_deref().unwrap_or("?"),
+            self.end_date.as_deref().unwrap_or("?"),
+            self.course_count,
+            if self.is_current { " (current)" } else { "" }
+        )
+    }
+}
+
+impl Table for Vec<Semester> {
+    fn write_table(&self, out: &mut dyn Write) -> io::Result<()> {
+        for semester in self {
+            semester.write_table(out)?;
+        }
+        Ok(())
+    }
+}
+
+impl Table for HashMessage {
+    fn write_table(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "Course cache is rebuilt. Hash: {}", self.hash)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MergeCourseGroups {
+    source_group_ids: Vec<i32>,
+}
+
+pub async fn groups(client: &ApiClient, command: GroupsCommand, output: OutputFormat, out: &mut dyn Write) -> Result<()> {
+    match command {
+        GroupsCommand::Merge { target, sources } => {
+            let body = MergeCourseGroups { source_group_ids: sources };
+            let group: CourseGroup = client.post(&format!("/group/{}/merge", target), &body).await?;
+            write_output(&group, output, out)
+        }
+    }
+}
+
+pub async fn cache(client: &ApiClient, command: CacheCommand, output: OutputFormat, out: &mut dyn Write) -> Result<()> {
+    match command {
+        CacheCommand::Refresh => {
+            // The server answers `418 I'm a teapot` on success.
+            let response = client.request(Method::GET, "/courses/refresh").send().await?;
+            let status = response.status();
+            if status != StatusCode::IM_A_TEAPOT && !status.is_success() {
+                bail!("Failed to refresh the course cache: {} {}", status, response.text().await.unwrap_or_default());
+            }
+            // Fetching the hash rebuilds the cache right away.
+            let hash: HashMessage = client.get("/courses/hash").await?;
+            write_output(&hash, output, out)
+        }
+    }
+}
+
+pub async fn achievements(
+    client: &ApiClient,
+    command: AchievementsCommand,
+    output: OutputFormat,
+    out: &mut dyn Write,
+) -> Result<()> {
+    match command {
+        AchievementsCommand::Grant {
+            user_id,
+            achievement_id,
+            obtain_date,
+        } => {
+            let body = json!({ "achievement_id": achievement_id, "obtain_date": obtain_date });
+            let achievements: Vec<Achievement> = client.post(&format!("/users/{}/achievements", user_id), &body).await?;
+            write_output(&achievements, output, out)
+        }
+        AchievementsCommand::Backfill => {
+            let result: BackfillResult = client.post("/achievements/backfill", &json!({})).await?;
+            write_output(&result, output, out)
+        }
+    }
+}
+
+pub async fn semesters(client: &ApiClient, command: SemestersCommand, output: OutputFormat, out: &mut dyn Write) -> Result<()> {
+    match command {
+        SemestersCommand::List => {
+            let semesters: Vec<Semester> = client.get("/semesters").await?;
+            write_output(&semesters, output, out)
+        }
+        SemestersCommand::Add {
+            year,
+            semester,
+            name,
+            start_date,
+            end_date,
+            current,
+        } => {
+            let body = json!({
+                "year": year,
+                "semester": semester,
+                "name": name,
+                "start_date": start_date,
+                "end_date": end_date,
+                "is_current": current,
+            });
+            let semester: Semester = client.post("/semesters", &body).await?;
+            write_output(&semester, output, out)
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+
+/// The body of `POST /courses`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NewCourse {
+    pub campus_name: String,
+    pub code: String,
+    pub code_id: String,
+    pub credit: f64,
+    pub department: String,
+    pub max_student: i32,
+    pub name: String,
+    pub semester: i32,
+    pub teachers: String,
+    pub week_hour: i32,
+    pub year: i32,
+}
+
+/// A course in the `GET /courses` listing of the server.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Course {
+    pub id: i32,
+    pub name: String,
+    pub code: String,
+    pub code_id: String,
+    pub credit: f64,
+    pub department: String,
+    pub campus_name: String,
+    pub teachers: String,
+    pub max_student: i32,
+    pub week_hour: i32,
+    pub year: i32,
+    pub semester: i32,
+}
+
+impl Course {
+    /// Whether the course is in the given year and semester. `None` matches any.
+    pub fn is_in(&self, year: Option<i32>, semester: Option<i32>) -> bool {
+        year.is_none_or(|year| self.year == year) && semester.is_none_or(|semester| self.semester == semester)
+    }
+}
+
+/// A semester in the `GET /semesters` listing of the server, or one returned by `POST /semesters`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Semester {
+    pub year: i32,
+    pub semester: i32,
+    /// `None` if the semester has courses but is not registered on the server.
+    #[serde(default)]
+    pub id: Option<i32>,
+    pub name: Option<String>,
+    /// The dates are optional, e.g. for semesters registered from existing courses.
+    #[serde(default)]
+    pub start_date: Option<String>,
+    #[serde(default)]
+    pub end_date: Option<String>,
+    #[serde(default)]
+    pub is_current: bool,
+    /// Not returned by `POST /semesters`.
+    #[serde(default)]
+    pub course_count: i64,
+}
+
+/// A course group in the `GET /courses` listing of the server.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CourseGroup {
+    pub id: i32,
+    pub name: String,
+    pub code: String,
+    pub department: String,
+    pub campus_name: String,
+    pub course_list: Vec<Course>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Review {
+    pub id: i32,
+    pub title: String,
+    pub content: String,
+    /// Only visible to admin.
+    pub reviewer_id: Option<i32>,
+    #[serde(default)]
+    pub reviewer_pseudonym: String,
+    pub time_created: String,
+    pub time_updated: String,
+    pub rank: serde_json::Value,
+    pub remark: i32,
+}
+
+/// A course returned by `GET /group/{group_id}`, with its reviews.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CourseWithReviews {
+    #[serde(flatten)]
+    pub course: Course,
+    pub review_list: Vec<Review>,
+}
+
+/// The course group returned by `GET /group/{group_id}`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CourseGroupWithReviews {
+    pub id: i32,
+    pub name: String,
+    pub code: String,
+    pub course_list: Vec<CourseWithReviews>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Achievement {
+    pub name: String,
+    pub domain: Option<String>,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub obtain_date: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BackfillResult {
+    pub users_evaluated: usize,
+    pub achievements_awarded: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HashMessage {
+    pub hash: String,
+}
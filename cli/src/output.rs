@@ -0,0 +1,24 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::{self, Write};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+/// A command result that can be printed for humans.
+pub trait Table {
+    fn write_table(&self, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Print a command result in the chosen format.
+pub fn write_output<T: Serialize + Table>(value: &T, format: OutputFormat, out: &mut dyn Write) -> Result<()> {
+    match format {
+        OutputFormat::Json => writeln!(out, "{}", serde_json::to_string_pretty(value)?)?,
+        OutputFormat::Table => value.write_table(out)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use crate::client::ApiClient;
+use crate::model::CourseGroup;
+use crate::output::{write_output, OutputFormat, Table};
+use anyhow::Result;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    io::{self, Write},
+};
+
+#[derive(Debug, Serialize)]
+pub struct SemesterStats {
+    pub year: i32,
+    pub semester: i32,
+    pub courses: usize,
+}
+
+/// An overview of the courses on the server.
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub groups: usize,
+    pub courses: usize,
+    pub departments: usize,
+    /// Sorted by year and semester.
+    pub semesters: Vec<SemesterStats>,
+}
+
+impl Stats {
+    pub fn new(groups: &[CourseGroup]) -> Self {
+        let courses = || groups.iter().flat_map(|group| group.course_list.iter());
+        let mut semesters: BTreeMap<(i32, i32), usize> = BTreeMap::new();
+        for course in courses() {
+            *semesters.entry((course.year, course.semester)).or_default() += 1;
+        }
+        Stats {
+            groups: groups.len(),
+            courses: courses().count(),
+            departments: courses().map(|course| course.department.as_str()).collect::<HashSet<_>>().len(),
+            semesters: semesters
+                .into_iter()
+                .map(|((year, semester), courses)| SemesterStats { year, semester, courses })
+                .collect(),
+        }
+    }
+}
+
+impl Table for Stats {
+    fn write_table(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "Course groups: {}", self.groups)?;
+        writeln!(out, "Courses:       {}", self.courses)?;
+        writeln!(out, "Departments:   {}", self.departments)?;
+        writeln!(out, "Courses by semester:")?;
+        for semester in &self.semesters {
+            writeln!(out, "  {}-{}  {}", semester.year, semester.semester, semester.courses)?;
+        }
+        Ok(())
+    }
+}
+
+pub async fn run(client: &ApiClient, output: OutputFormat, out: &mut dyn Write) -> Result<()> {
+    let groups: Vec<CourseGroup> = client.get("/courses").await?;
+    write_output(&Stats::new(&groups), output, out)
+}
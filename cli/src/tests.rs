@@ -0,0 +1,548 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests {
+    use crate::input::{parse_rows, read_csv, worksheet_rows, ColumnMapping};
+    use crate::{run, Cli};
+    use calamine::{Data, Range};
+    use clap::Parser;
+    use mockito::{Matcher, Server, ServerGuard};
+    use serde_json::{json, Value};
+    use std::path::PathBuf;
+
+    fn course_groups() -> Value {
+        json!([
+            {
+                "id": 1,
+                "name": "Data Structures",
+                "code": "COMP130004",
+                "department": "Computer Science",
+                "campus_name": "Handan",
+                "course_list": [
+                    {
+                        "id": 10, "name": "Data Structures", "code": "COMP130004", "code_id": "COMP130004.01",
+                        "credit": 3.0, "department": "Computer Science", "campus_name": "Handan", "teachers": "Alice",
+                        "max_student": 100, "week_hour": 3, "year": 2022, "semester": 1, "coursegroup_id": 1
+                    },
+                    {
+                        "id": 11, "name": "Data Structures", "code": "COMP130004", "code_id": "COMP130004.01",
+                        "credit": 3.0, "department": "Computer Science", "campus_name": "Handan", "teachers": "Bob",
+                        "max_student": 100, "week_hour": 3, "year": 2023, "semester": 1, "coursegroup_id": 1
+                    }
+                ]
+            },
+            {
+                "id": 2,
+                "name": "Mathematical Analysis",
+                "code": "MATH120001",
+                "department": "Mathematics",
+                "campus_name": "Handan",
+                "course_list": [
+                    {
+                        "id": 20, "name": "Mathematical Analysis", "code": "MATH120001", "code_id": "MATH120001.01",
+                        "credit": 5.0, "department": "Mathematics", "campus_name": "Handan", "teachers": "Carol",
+                        "max_student": 100, "week_hour": 5, "year": 2022, "semester": 1, "coursegroup_id": 2
+                    }
+                ]
+            }
+        ])
+    }
+
+    async fn mock_server() -> ServerGuard {
+        let mut server = Server::new_async().await;
+        server
+            .mock("GET", "/courses")
+            .with_header("content-type", "application/json")
+            .with_body(course_groups().to_string())
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/semesters")
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!([
+                    { "year": 2023, "semester": 1, "id": null, "name": null, "start_date": null, "end_date": null, "is_current": false, "course_count": 1 },
+                    { "year": 2022, "semester": 1, "id": 1, "name": "2022-2023 学年秋季学期", "start_date": "2022-09-01", "end_date": "2023-01-15", "is_current": true, "course_count": 2 }
+                ])
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        server
+    }
+
+    async fn run_command(server: &ServerGuard, args: &[&str]) -> anyhow::Result<String> {
+        let url = server.url();
+        let mut argv = vec!["cli", "--url", url.as_str(), "--auth-token", "token"];
+        argv.extend_from_slice(args);
+        let cli = Cli::try_parse_from(argv)?;
+        let mut out = vec![];
+        run(cli, &mut out).await?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    /// A fresh directory for the files of a test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("curriculum-cli-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_import() {
+        let mut server = mock_server().await;
+        let post = server
+            .mock("POST", "/courses")
+            .match_header("authorization", "Bearer token")
+            .match_body(Matcher::PartialJson(json!({ "code_id": "COMP130005.01", "year": 2022, "semester": 1 })))
+            .with_body("{}")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let dir = temp_dir("import");
+        let file = dir.join("courses.csv");
+        std::fs::write(
+            &file,
+            "课程名称,课程序号,教师,学分,开课院系\n\
+             Data Structures,COMP130004.01,Alice,3,Computer Science\n\
+             Algorithms,COMP130005.01,Alice,3,Computer Science\n",
+        )
+        .unwrap();
+        let file = file.to_str().unwrap();
+        let columns = [
+            "--column", "name=课程名称", "--column", "no=课程序号", "--column", "teachers=教师",
+            "--column", "credits=学分", "--column", "department=开课院系",
+        ];
+
+        // the existing course is skipped
+        let mut args = vec!["import", "-j", file, "-y", "2022", "-s", "1"];
+        args.extend_from_slice(&columns);
+        let out = run_command(&server, &args).await.unwrap();
+        assert!(out.contains("All courses have been imported successfully"));
+        post.assert_async().await;
+        let report: Value = serde_json::from_str(&std::fs::read_to_string(format!("{}.report.json", file)).unwrap()).unwrap();
+        assert_eq!(report["imported"], 1);
+        assert_eq!(report["skipped"], 1);
+
+        // nothing is sent in dry-run mode
+        let mut args = vec!["--output", "json", "import", "-j", file, "-y", "2022", "-s", "1", "--dry-run"];
+        args.extend_from_slice(&columns);
+        let out = run_command(&server, &args).await.unwrap();
+        let diff: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(diff["new_courses"][0]["code_id"], "COMP130005.01");
+        assert_eq!(diff["new_groups"][0]["code"], "COMP130005");
+        assert_eq!(diff["missing_courses"][0]["code_id"], "MATH120001.01");
+        post.assert_async().await;
+
+        // semesters not registered on the server are rejected before anything is sent
+        let mut args = vec!["import", "-j", file, "-y", "2023", "-s", "1"];
+        args.extend_from_slice(&columns);
+        let err = run_command(&server, &args).await.unwrap_err();
+        assert!(err.to_string().contains("Semester 1 of year 2023 is not registered"));
+        assert!(err.to_string().contains("2022-1 (2022-2023 学年秋季学期)"));
+        post.assert_async().await;
+    }
+
+    /// Write courses in the JSON format of the course selection system.
+    fn write_json_courses(file: &std::path::Path, codes: &[&str]) {
+        let courses: Vec<Value> = codes
+            .iter()
+            .map(|no| json!({
+                "name": "Course", "no": no, "teachDepartName": "Computer Science", "teachers": "Alice",
+                "credits": 3.0, "maxStudent": 100, "campusName": "Handan", "weekHour": 3
+            }))
+            .collect();
+        std::fs::write(file, Value::from(courses).to_string()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_import_resume() {
+        let mut server = mock_server().await;
+        let imported = server
+            .mock("POST", "/courses")
+            .match_body(Matcher::PartialJson(json!({ "code_id": "COMP130005.01" })))
+            .with_body("{}")
+            .expect(1)
+            .create_async()
+            .await;
+        let rejected = server
+            .mock("POST", "/courses")
+            .match_body(Matcher::PartialJson(json!({ "code_id": "COMP130006.01" })))
+            .with_status(400)
+            .with_body(json!({ "code": "INVALID_REQUEST", "message": "bad course" }).to_string())
+            .expect(2)
+            .create_async()
+            .await;
+
+        let dir = temp_dir("import-resume");
+        let file = dir.join("courses.json");
+        write_json_courses(&file, &["COMP130004.01", "COMP130005.01", "COMP130006.01"]);
+        let file = file.to_str().unwrap();
+        let checkpoint = dir.join("checkpoint.txt");
+        let checkpoint = checkpoint.to_str().unwrap();
+        let report_file = dir.join("report.json");
+        let report_file = report_file.to_str().unwrap();
+        let args = ["import", "-j", file, "-y", "2022", "-s", "1", "--checkpoint", checkpoint, "--report", report_file];
+
+        // failed courses are listed in the report, imported ones in the checkpoint
+        let out = run_command(&server, &args).await.unwrap();
+        assert!(out.contains("1 courses failed to import"));
+        let report: Value = serde_json::from_str(&std::fs::read_to_string(report_file).unwrap()).unwrap();
+        assert_eq!(report["total"], 3);
+        assert_eq!(report["imported"], 1);
+        assert_eq!(report["skipped"], 1);
+        assert_eq!(report["interrupted"], false);
+        assert_eq!(report["failed"].as_array().unwrap().len(), 1);
+        assert_eq!(report["failed"][0]["course"]["code_id"], "COMP130006.01");
+        assert_eq!(report["failed"][0]["status"], 400);
+        assert!(report["failed"][0]["body"].as_str().unwrap().contains("bad course"));
+        assert_eq!(std::fs::read_to_string(checkpoint).unwrap(), "COMP130005.01\n");
+
+        // resuming skips the checkpointed course and only retries the failed one
+        let mut resume = args.to_vec();
+        resume.push("--resume");
+        run_command(&server, &resume).await.unwrap();
+        imported.assert_async().await;
+        rejected.assert_async().await;
+        let report: Value = serde_json::from_str(&std::fs::read_to_string(report_file).unwrap()).unwrap();
+        assert_eq!(report["imported"], 0);
+        assert_eq!(report["skipped"], 2);
+        assert_eq!(std::fs::read_to_string(checkpoint).unwrap(), "COMP130005.01\n");
+
+        // without `--resume` the checkpoint starts over instead of being appended to
+        run_command(&server, &args).await.unwrap();
+        assert_eq!(std::fs::read_to_string(checkpoint).unwrap(), "COMP130005.01\n");
+    }
+
+    #[tokio::test]
+    async fn test_import_dry_run() {
+        let mut server = mock_server().await;
+        let post = server.mock("POST", "/courses").expect(0).create_async().await;
+
+        let dir = temp_dir("import-dry-run");
+        let file = dir.join("courses.json");
+        std::fs::write(&file, json!([
+            // changed teachers and credit
+            { "name": "Data Structures", "no": "COMP130004.01", "teachDepartName": "Computer Science", "teachers": "Bob",
+              "credits": 4.0, "maxStudent": 100, "campusName": "Handan", "weekHour": 3 },
+            // a new course of an existing group
+            { "name": "Data Structures", "no": "COMP130004.02", "teachDepartName": "Computer Science", "teachers": "Dave",
+              "credits": 3.0, "maxStudent": 100, "campusName": "Handan", "weekHour": 3 },
+            // a new course of a new group
+            { "name": "Compilers", "no": "COMP140001.01", "teachDepartName": "Computer Science", "teachers": "Eve",
+              "credits": 2.0, "maxStudent": 50, "campusName": "Handan", "weekHour": 2 },
+            // ignored, the same as a real import
+            { "name": "Too Short", "no": "AB", "teachDepartName": "Nowhere", "teachers": "Nobody",
+              "credits": 1.0, "maxStudent": 1, "campusName": "Handan", "weekHour": 1 }
+        ]).to_string()).unwrap();
+        let file = file.to_str().unwrap();
+
+        let out = run_command(&server, &["-o", "json", "import", "-j", file, "-y", "2022", "-s", "1", "--dry-run"]).await.unwrap();
+        let diff: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(diff["new_groups"], json!([{ "code": "COMP140001", "name": "Compilers" }]));
+        let new_courses: Vec<&str> = diff["new_courses"].as_array().unwrap().iter().map(|c| c["code_id"].as_str().unwrap()).collect();
+        assert_eq!(new_courses, ["COMP130004.02", "COMP140001.01"]);
+        assert_eq!(diff["changed_courses"], json!([{
+            "code_id": "COMP130004.01", "name": "Data Structures",
+            "old_teachers": "Alice", "new_teachers": "Bob", "old_credit": 3.0, "new_credit": 4.0
+        }]));
+        // only courses of the imported semester are compared
+        let missing: Vec<&str> = diff["missing_courses"].as_array().unwrap().iter().map(|c| c["code_id"].as_str().unwrap()).collect();
+        assert_eq!(missing, ["MATH120001.01"]);
+
+        let out = run_command(&server, &["import", "-j", file, "-y", "2022", "-s", "1", "--dry-run"]).await.unwrap();
+        assert!(out.contains("New groups (1):"));
+        assert!(out.contains("New courses (2):"));
+        assert!(out.contains("Changed courses (1):"));
+        assert!(out.contains("teachers: Alice -> Bob"));
+        assert!(out.contains("credit: 3 -> 4"));
+        assert!(out.contains("Missing from the file (1):"));
+        post.assert_async().await;
+        assert!(!std::path::Path::new(&format!("{}.checkpoint", file)).exists());
+    }
+
+    #[tokio::test]
+    async fn test_import_retry() {
+        let mut server = mock_server().await;
+        // the first course is created although the server answers 503, the second one is not
+        let created = server
+            .mock("POST", "/courses")
+            .match_body(Matcher::PartialJson(json!({ "code_id": "COMP130005.01" })))
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+        let failing = server
+            .mock("POST", "/courses")
+            .match_body(Matcher::PartialJson(json!({ "code_id": "COMP130006.01" })))
+            .with_status(503)
+            .with_body("unavailable")
+            .expect(2)
+            .create_async()
+            .await;
+        let mut groups = course_groups();
+        groups[0]["course_list"].as_array_mut().unwrap().push(json!({
+            "id": 12, "name": "Course", "code": "COMP130005", "code_id": "COMP130005.01",
+            "credit": 3.0, "department": "Computer Science", "campus_name": "Handan", "teachers": "Alice",
+            "max_student": 100, "week_hour": 3, "year": 2022, "semester": 1, "coursegroup_id": 1
+        }));
+        let lookup = server
+            .mock("GET", "/courses?year=2022&semester=1")
+            .with_body(groups.to_string())
+            .expect(2)
+            .create_async()
+            .await;
+
+        let dir = temp_dir("import-retry");
+        let file = dir.join("courses.json");
+        write_json_courses(&file, &["COMP130005.01", "COMP130006.01"]);
+        let file = file.to_str().unwrap();
+        let out = run_command(&server, &["import", "-j", file, "-y", "2022", "-s", "1", "--retries", "1", "--concurrency", "2"])
+            .await
+            .unwrap();
+        assert!(out.contains("1 courses failed to import"));
+        // the existing course is not posted again
+        created.assert_async().await;
+        failing.assert_async().await;
+        lookup.assert_async().await;
+
+        let report: Value = serde_json::from_str(&std::fs::read_to_string(format!("{}.report.json", file)).unwrap()).unwrap();
+        assert_eq!(report["imported"], 1);
+        assert_eq!(report["failed"][0]["course"]["code_id"], "COMP130006.01");
+        assert_eq!(report["failed"][0]["status"], 503);
+    }
+
+    #[test]
+    fn test_input_row_numbers() {
+        let mapping = ColumnMapping::default();
+
+        // blank lines are counted in the row numbers of a CSV file
+        let csv = "name,no,teachers,credits,department,maxStudent\n\n\
+                   Algorithms,COMP130005.01,Alice,3,Computer Science,30.0\n\
+                   \n\
+                   Compilers,,Bob,two,Computer Science,many\n";
+        let rows = read_csv(csv).unwrap();
+        assert_eq!(rows.iter().map(|(line, _)| *line).collect::<Vec<_>>(), [1, 3, 5]);
+        let err = parse_rows(rows, &mapping).unwrap_err().to_string();
+        assert_eq!(err, "Invalid rows:\nrow 5: empty `no`, invalid `credits` `two`, invalid `maxStudent` `many`");
+        let rows = read_csv("name,no,teachers,credits,department\nAlgorithms,COMP130005.01,Alice,3,Computer Science\n").unwrap();
+        let courses = parse_rows(rows, &mapping).unwrap();
+        assert_eq!(courses[0].no, "COMP130005.01");
+        assert_eq!(courses[0].maxStudent, 0);
+
+        // the used range of a worksheet may start below the first row
+        let mut range = Range::new((2, 1), (4, 5));
+        for (column, header) in ["name", "no", "teachers", "credits", "department"].iter().enumerate() {
+            range.set_value((2, column as u32 + 1), Data::String(header.to_string()));
+        }
+        for (column, value) in ["Algorithms", "COMP130005.01", "Alice", "3", "Computer Science"].iter().enumerate() {
+            range.set_value((3, column as u32 + 1), Data::String(value.to_string()));
+        }
+        range.set_value((4, 1), Data::String("Compilers".to_string()));
+        range.set_value((4, 2), Data::String("COMP140001.01".to_string()));
+        range.set_value((4, 4), Data::Float(2.0));
+        range.set_value((4, 5), Data::String("Computer Science".to_string()));
+        let rows = worksheet_rows(&range);
+        assert_eq!(rows.iter().map(|(row, _)| *row).collect::<Vec<_>>(), [3, 4, 5]);
+        let courses = parse_rows(rows.clone(), &mapping).unwrap();
+        assert_eq!(courses.len(), 2);
+        assert_eq!(courses[1].credits, 2.0);
+        let mut rows = rows;
+        rows[2].1[3] = "?".to_string();
+        let err = parse_rows(rows, &mapping).unwrap_err().to_string();
+        assert_eq!(err, "Invalid rows:\nrow 5: invalid `credits` `?`");
+
+        // missing columns are reported before any row
+        let rows = read_csv("name,no\n").unwrap();
+        let err = parse_rows(rows, &mapping).unwrap_err().to_string();
+        assert_eq!(err, "Missing required columns in the header row: teachers, credits, department");
+    }
+
+    #[tokio::test]
+    async fn test_export_courses() {
+        let server = mock_server().await;
+        let out = run_command(&server, &["export", "courses", "--format", "csv", "--year", "2022"]).await.unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(lines[0].starts_with("group_id,group_code,id,name"));
+        assert_eq!(lines.len(), 3);
+
+        let dir = temp_dir("export");
+        let file = dir.join("courses.json");
+        let file = file.to_str().unwrap();
+        let out = run_command(&server, &["export", "courses", "-O", file]).await.unwrap();
+        assert!(out.is_empty());
+        let courses: Value = serde_json::from_str(&std::fs::read_to_string(file).unwrap()).unwrap();
+        assert_eq!(courses.as_array().unwrap().len(), 3);
+        assert_eq!(courses[2]["group_code"], "MATH120001");
+    }
+
+    #[tokio::test]
+    async fn test_export_reviews() {
+        let mut server = mock_server().await;
+        let mut group = course_groups()[0].clone();
+        for course in group["course_list"].as_array_mut().unwrap() {
+            course["review_list"] = json!([{
+                "id": course["id"].as_i64().unwrap() * 100,
+                "title": "Great",
+                "content": "Learned a lot, \"really\".",
+                "history": [],
+                "reviewer_id": null,
+                "reviewer_pseudonym": "0123456789ab",
+                "time_created": "2023-01-01T00:00:00",
+                "time_updated": "2023-01-01T00:00:00",
+                "rank": { "overall": 5 },
+                "is_me": false,
+                "vote": 0,
+                "remark": 2,
+                "extra": null
+            }]);
+        }
+        let group_mock = server
+            .mock("GET", "/group/1")
+            .match_header("authorization", "Bearer token")
+            .with_body(group.to_string())
+            .expect(1)
+            .create_async()
+            .await;
+
+        // only group 1 has courses in 2023
+        let out = run_command(&server, &["export", "reviews", "--year", "2023"]).await.unwrap();
+        group_mock.assert_async().await;
+        let reviews: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(reviews.as_array().unwrap().len(), 1);
+        assert_eq!(reviews[0]["review_id"], 1100);
+        assert_eq!(reviews[0]["reviewer_pseudonym"], "0123456789ab");
+        assert_eq!(reviews[0]["rank"], r#"{"overall":5}"#);
+    }
+
+    #[tokio::test]
+    async fn test_groups_merge() {
+        let mut server = mock_server().await;
+        let merged = json!({
+            "id": 1,
+            "name": "Data Structures",
+            "code": "COMP130004",
+            "department": "Computer Science",
+            "campus_name": "Handan",
+            "course_list": course_groups()[0]["course_list"]
+        });
+        server
+            .mock("POST", "/group/1/merge")
+            .match_body(Matcher::Json(json!({ "source_group_ids": [2, 3] })))
+            .with_body(merged.to_string())
+            .create_async()
+            .await;
+        let out = run_command(&server, &["groups", "merge", "1", "2", "3"]).await.unwrap();
+        assert!(out.starts_with("Group 1 COMP130004 Data Structures (2 courses):"));
+
+        // at least one source is required
+        assert!(run_command(&server, &["groups", "merge", "1"]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cache_refresh() {
+        let mut server = mock_server().await;
+        let refresh = server
+            .mock("GET", "/courses/refresh")
+            .with_status(418)
+            .with_body("I'm a brand new empty teapot now!")
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/courses/hash")
+            .with_body(json!({ "hash": "abcdef" }).to_string())
+            .create_async()
+            .await;
+        let out = run_command(&server, &["-o", "json", "cache", "refresh"]).await.unwrap();
+        refresh.assert_async().await;
+        assert_eq!(serde_json::from_str::<Value>(&out).unwrap()["hash"], "abcdef");
+    }
+
+    #[tokio::test]
+    async fn test_achievements_grant() {
+        let mut server = mock_server().await;
+        server
+            .mock("POST", "/users/5/achievements")
+            .match_body(Matcher::Json(json!({ "achievement_id": 3, "obtain_date": null })))
+            .with_body(json!([{
+                "name": "First Review",
+                "domain": "curriculum",
+                "description": null,
+                "icon": null,
+                "obtain_date": "2023-01-01T00:00:00"
+            }]).to_string())
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/users/6/achievements")
+            .with_status(409)
+            .with_body(json!({ "message": "User 6 already has achievement 3." }).to_string())
+            .create_async()
+            .await;
+
+        let out = run_command(&server, &["achievements", "grant", "5", "3"]).await.unwrap();
+        assert!(out.starts_with("First Review"));
+        let err = run_command(&server, &["achievements", "grant", "6", "3"]).await.unwrap_err();
+        assert!(err.to_string().contains("User 6 already has achievement 3."));
+    }
+
+    #[tokio::test]
+    async fn test_achievements_backfill() {
+        let mut server = mock_server().await;
+        server
+            .mock("POST", "/achievements/backfill")
+            .with_body(json!({ "users_evaluated": 4, "achievements_awarded": 2 }).to_string())
+            .create_async()
+            .await;
+        let out = run_command(&server, &["achievements", "backfill"]).await.unwrap();
+        assert_eq!(out.trim(), "Evaluated 4 users, awarded 2 achievements.");
+    }
+
+    #[tokio::test]
+    async fn test_semesters() {
+        let mut server = mock_server().await;
+        let out = run_command(&server, &["semesters", "list"]).await.unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].contains("(not registered)"));
+        assert!(lines[1].contains("2022-2023 学年秋季学期"));
+        assert!(lines[1].contains("2022-09-01 ~ 2023-01-15"));
+        assert!(lines[1].ends_with("(current)"));
+
+        let mock = server
+            .mock("POST", "/semesters")
+            .match_body(Matcher::Json(json!({
+                "year": 2023, "semester": 1, "name": "2023-2024 学年秋季学期",
+                "start_date": "2023-09-01", "end_date": null, "is_current": false
+            })))
+            .with_body(json!({
+                "id": 2, "year": 2023, "semester": 1, "name": "2023-2024 学年秋季学期",
+                "start_date": "2023-09-01", "end_date": null, "is_current": false
+            }).to_string())
+            .create_async()
+            .await;
+        let out = run_command(&server, &["--output", "json", "semesters", "add", "2023", "1", "--name", "2023-2024 学年秋季学期", "--start-date", "2023-09-01"])
+            .await
+            .unwrap();
+        mock.assert_async().await;
+        let semester: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(semester["id"], 2);
+        assert!(semester["end_date"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_stats() {
+        let server = mock_server().await;
+        let out = run_command(&server, &["--output", "json", "stats"]).await.unwrap();
+        let stats: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(stats["groups"], 2);
+        assert_eq!(stats["courses"], 3);
+        assert_eq!(stats["departments"], 2);
+        assert_eq!(stats["semesters"], json!([
+            { "year": 2022, "semester": 1, "courses": 2 },
+            { "year": 2023, "semester": 1, "courses": 1 }
+        ]));
+    }
+}
@@ -1,18 +1,26 @@
 use sea_orm::entity::prelude::*;
+use sea_orm::ActiveValue::{NotSet, Set};
 use utoipa::ToSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::user_achievement;
 
+/// 名称和领域的最大字符数，与 MySQL 中两列的长度相同
+pub const MAX_NAME_LENGTH: usize = 255;
+
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize, DeriveEntityModel, ToSchema)]
 #[sea_orm(table_name = "achievement")]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
-    #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
+    #[sea_orm(column_type = "Text")]
     pub name: String,
-    #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
+    #[sea_orm(column_type = "Text")]
     pub domain: Option<String>,
+    #[sea_orm(column_type = "Text")]
+    pub description: Option<String>,
+    #[sea_orm(column_type = "Text")]
+    pub icon: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -29,3 +37,62 @@ impl Related<user_achievement::Entity> for Entity {
 
 impl ActiveModelBehavior for ActiveModel {}
 
+impl Entity {
+    /// 名称和领域都相同的成就。名称和领域共同唯一，领域为空时只匹配同样没有领域的成就
+    pub fn find_by_name_and_domain(name: &str, domain: Option<&str>) -> Select<Entity> {
+        Self::find().filter(Column::Name.eq(name)).filter(match domain {
+            Some(domain) => Column::Domain.eq(domain),
+            None => Column::Domain.is_null(),
+        })
+    }
+}
+
+impl From<Model> for GetAchievementInfo {
+    fn from(model: Model) -> Self {
+        GetAchievementInfo {
+            id: model.id,
+            name: model.name,
+            domain: model.domain,
+            description: model.description,
+            icon: model.icon,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct GetAchievementInfo {
+    pub id: i32,
+    pub name: String,
+    pub domain: Option<String>,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct NewAchievement {
+    pub name: String,
+    pub domain: Option<String>,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+}
+
+impl NewAchievement {
+    pub fn into_active_model(self) -> ActiveModel {
+        ActiveModel {
+            id: NotSet,
+            name: Set(self.name),
+            domain: Set(self.domain),
+            description: Set(self.description),
+            icon: Set(self.icon),
+        }
+    }
+}
+
+impl ActiveModel {
+    pub fn update_with(&mut self, updated_achievement: NewAchievement) {
+        self.name = Set(updated_achievement.name);
+        self.domain = Set(updated_achievement.domain);
+        self.description = Set(updated_achievement.description);
+        self.icon = Set(updated_achievement.icon);
+    }
+}
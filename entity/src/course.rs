@@ -2,6 +2,7 @@
 
 use crate::review;
 use crate::review::GetReview;
+use crate::semester::SemesterNames;
 use sea_orm::{entity::prelude::*, ActiveValue::NotSet};
 use sea_orm::{ModelTrait, Set};
 use serde::{Deserialize, Serialize};
@@ -12,18 +13,18 @@ use utoipa::ToSchema;
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
-    #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
+    #[sea_orm(column_type = "Text")]
     pub name: String,
-    #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
+    #[sea_orm(column_type = "Text")]
     pub code: String,
-    #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
+    #[sea_orm(column_type = "Text")]
     pub code_id: String,
     pub credit: f64,
-    #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
+    #[sea_orm(column_type = "Text")]
     pub department: String,
-    #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
+    #[sea_orm(column_type = "Text")]
     pub campus_name: String,
-    #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
+    #[sea_orm(column_type = "Text")]
     pub teachers: String,
     pub max_student: i32,
     pub week_hour: i32,
@@ -78,23 +79,60 @@ impl From<Model> for GetSingleCourse {
             week_hour: model.week_hour,
             year: model.year,
             semester: model.semester,
+            semester_name: None,
             review_list: vec![],
         }
     }
 }
 
+impl From<Model> for GetMultiCourse {
+    fn from(model: Model) -> Self {
+        GetMultiCourse {
+            id: model.id,
+            name: model.name,
+            code: model.code,
+            code_id: model.code_id,
+            credit: model.credit,
+            department: model.department,
+            campus_name: model.campus_name,
+            teachers: model.teachers,
+            max_student: model.max_student,
+            week_hour: model.week_hour,
+            year: model.year,
+            semester: model.semester,
+            semester_name: None,
+            coursegroup_id: model.coursegroup_id,
+        }
+    }
+}
+
 impl GetSingleCourse {
-    pub async fn load(model: Model, db: &DatabaseConnection, user_id: i32) -> Result<Self, DbErr> {
+    /// `with_extra` 为 `false` 时不加载评论者的成就信息，以减小响应体积
+    pub async fn load(model: Model, db: &DatabaseConnection, user_id: i32, with_extra: bool) -> Result<Self, DbErr> {
         let review_raw_list: Vec<review::Model> =
             model.find_related(review::Entity).all(db).await?;
         let mut course: Self = model.into();
         let mut review_list: Vec<GetReview> = vec![];
         for review in review_raw_list {
-            review_list.push(GetReview::load(review, db, user_id).await?);
+            if with_extra {
+                review_list.push(GetReview::load(review, db, user_id).await?);
+            } else {
+                review_list.push(GetReview::new(review, user_id));
+            }
         }
         course.review_list = review_list;
         Ok(course)
     }
+
+    pub fn set_semester_name(&mut self, names: &SemesterNames) {
+        self.semester_name = names.get(self.year, self.semester);
+    }
+}
+
+impl GetMultiCourse {
+    pub fn set_semester_name(&mut self, names: &SemesterNames) {
+        self.semester_name = names.get(self.year, self.semester);
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
@@ -111,9 +149,31 @@ pub struct GetSingleCourse {
     pub week_hour: i32,
     pub year: i32,
     pub semester: i32,
+    /// 所在学期的名称，学期未登记时为空
+    pub semester_name: Option<String>,
     pub review_list: Vec<GetReview>,
 }
 
+/// 课程组列表中的课程，不含评论
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct GetMultiCourse {
+    pub id: i32,
+    pub name: String,
+    pub code: String,
+    pub code_id: String,
+    pub credit: f64,
+    pub department: String,
+    pub campus_name: String,
+    pub teachers: String,
+    pub max_student: i32,
+    pub week_hour: i32,
+    pub year: i32,
+    pub semester: i32,
+    /// 所在学期的名称，学期未登记时为空
+    pub semester_name: Option<String>,
+    pub coursegroup_id: Option<i32>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
 pub struct NewCourse {
     pub name: String,
@@ -1,7 +1,8 @@
 //! SeaORM Entity. Generated by sea-orm-codegen 0.8.0
 
 use sea_orm::entity::prelude::*;
-use crate::course::{GetSingleCourse, NewCourse};
+use crate::course::{GetMultiCourse, GetSingleCourse, NewCourse};
+use crate::semester::SemesterNames;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use crate::course;
@@ -11,13 +12,13 @@ use crate::course;
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
-    #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
+    #[sea_orm(column_type = "Text")]
     pub name: String,
-    #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
+    #[sea_orm(column_type = "Text")]
     pub code: String,
-    #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
+    #[sea_orm(column_type = "Text")]
     pub department: String,
-    #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
+    #[sea_orm(column_type = "Text")]
     pub campus_name: String,
 }
 
@@ -84,13 +85,20 @@ pub struct GetMultiCourseGroup {
     pub code: String,
     pub department: String,
     pub campus_name: String,
-    pub course_list: Vec<course::Model>,
+    pub course_list: Vec<GetMultiCourse>,
 }
 
 impl GetMultiCourseGroup {
-    pub fn new(model: Model, course_list: Vec<course::Model>) -> GetMultiCourseGroup {
+    pub fn new(model: Model, course_list: Vec<course::Model>, names: &SemesterNames) -> GetMultiCourseGroup {
         let mut group: GetMultiCourseGroup = model.into();
-        group.course_list = course_list;
+        group.course_list = course_list
+            .into_iter()
+            .map(|course| {
+                let mut course = GetMultiCourse::from(course);
+                course.semester_name = names.get(course.year, course.semester);
+                course
+            })
+            .collect();
         group
     }
 }
@@ -0,0 +1,30 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 用户数据擦除的审计记录
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize, DeriveEntityModel, ToSchema)]
+#[sea_orm(table_name = "erasure_audit")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    /// 被擦除数据的用户
+    pub user_id: i32,
+    /// 发起擦除的用户，本人或管理员
+    pub requested_by: i32,
+    /// `anonymize` 或 `delete`
+    #[sea_orm(column_type = "Text")]
+    pub mode: String,
+    /// 被匿名化或删除的评论数
+    pub reviews_affected: i32,
+    /// 被移除的投票数
+    pub votes_removed: i32,
+    /// 被移除的成就数
+    pub achievements_removed: i32,
+    pub time_created: DateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -4,3 +4,5 @@ pub mod coursegroup;
 pub mod review;
 pub mod achievement;
 pub mod user_achievement;
+pub mod erasure_audit;
+pub mod semester;
@@ -5,3 +5,5 @@ pub use super::coursegroup::Entity as Coursegroup;
 pub use super::review::Entity as Review;
 pub use super::achievement::Entity as Achievement;
 pub use super::user_achievement::Entity as UserAchievement;
+pub use super::erasure_audit::Entity as ErasureAudit;
+pub use super::semester::Entity as Semester;
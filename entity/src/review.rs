@@ -1,5 +1,6 @@
 //! SeaORM Entity. Generated by sea-orm-codegen 0.8.0
 
+use std::sync::OnceLock;
 use std::time::Duration;
 
 use crate::{course, user_achievement::GetAchievement};
@@ -18,20 +19,27 @@ use utoipa::ToSchema;
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
-    #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
+    #[sea_orm(column_type = "Text")]
     pub title: String,
-    #[sea_orm(column_type = "custom(\"LONGTEXT\")")]
+    #[sea_orm(column_type = "Text")]
     pub content: String,
+    #[sea_orm(column_type = "JsonBinary")]
     pub history: Json,
     pub reviewer_id: i32,
     pub time_created: DateTime,
     pub time_updated: DateTime,
+    #[sea_orm(column_type = "JsonBinary")]
     pub rank: Json,
+    #[sea_orm(column_type = "JsonBinary")]
     pub upvoters: Json,
+    #[sea_orm(column_type = "JsonBinary")]
     pub downvoters: Json,
     pub course_id: Option<i32>,
 }
 
+/// 用户擦除数据后，其匿名化评论的 `reviewer_id`
+pub const ERASED_REVIEWER_ID: i32 = 0;
+
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(
@@ -68,22 +76,31 @@ fn _calculate_votes(model: &Model, user_id: i32) -> (i32, i32, i32) {
 }
 
 impl GetReview {
-    pub async fn load(model: Model, db: &DatabaseConnection, user_id: i32) -> Result<Self, DbErr> {
+    /// 不包含评论者的成就信息，`extra` 为空
+    pub fn new(model: Model, user_id: i32) -> Self {
         let (upvote, downvote, voted) = _calculate_votes(&model, user_id);
-        Ok(GetReview {
+        GetReview {
             id: model.id,
             title: model.title,
             content: model.content,
             history: model.history,
-            reviewer_id: model.reviewer_id,
+            reviewer_id: Some(model.reviewer_id),
+            reviewer_pseudonym: String::new(),
             time_created: model.time_created,
             time_updated: model.time_updated,
             rank: model.rank,
             is_me: model.reviewer_id == user_id,
             remark: upvote - downvote,
             vote: voted,
-            extra: Some(Userextra::load(model.reviewer_id, db).await?),
-        })
+            extra: None,
+        }
+    }
+
+    pub async fn load(model: Model, db: &DatabaseConnection, user_id: i32) -> Result<Self, DbErr> {
+        let reviewer_id = model.reviewer_id;
+        let mut review = GetReview::new(model, user_id);
+        review.extra = Some(Userextra::load(reviewer_id, db).await?);
+        Ok(review)
     }
 }
 
@@ -92,8 +109,12 @@ pub struct GetReview {
     pub id: i32,
     pub title: String,
     pub content: String,
+    /// 对非管理员，`alter_by` 和 `original.reviewer_id` 为评论者在本课程下的假名
     pub history: Json,
-    pub reviewer_id: i32,
+    /// 仅管理员可见
+    pub reviewer_id: Option<i32>,
+    /// 评论者在本课程下的假名
+    pub reviewer_pseudonym: String,
     pub time_created: DateTime,
     pub time_updated: DateTime,
     pub rank: Json,
@@ -103,16 +124,43 @@ pub struct GetReview {
     pub extra: Option<Userextra>,
 }
 
+// 缓存的容量和存活时间，须在第一次使用缓存之前设置
+static USER_EXTRA_CACHE_LIMIT: OnceLock<(u64, Option<Duration>)> = OnceLock::new();
+// 每次查询缓存后调用，参数为是否命中，用于统计命中率
+static USER_EXTRA_CACHE_OBSERVER: OnceLock<fn(bool)> = OnceLock::new();
+
 // 为减少数据库查询次数，缓存用户信息
 lazy_static! {
-    static ref GLOBAL_USER_EXTRA_CACHE: Cache<i32, Userextra> = CacheBuilder::new(10000)
-        .time_to_live(Duration::from_secs(60 * 5))
-        .build();
+    static ref GLOBAL_USER_EXTRA_CACHE: Cache<i32, Userextra> = {
+        let (capacity, time_to_live) = USER_EXTRA_CACHE_LIMIT
+            .get()
+            .copied()
+            .unwrap_or((10000, Some(Duration::from_secs(60 * 5))));
+        let mut builder = CacheBuilder::new(capacity);
+        if let Some(time_to_live) = time_to_live {
+            builder = builder.time_to_live(time_to_live);
+        }
+        builder.build()
+    };
 }
 
 impl Userextra {
+    /// 设置缓存的容量和存活时间。缓存创建之后再设置无效，返回 `false`
+    pub fn configure_cache(capacity: u64, time_to_live: Option<Duration>) -> bool {
+        USER_EXTRA_CACHE_LIMIT.set((capacity, time_to_live)).is_ok()
+    }
+
+    /// 设置缓存命中与否的回调。只能设置一次，重复设置返回 `false`
+    pub fn observe_cache(observer: fn(bool)) -> bool {
+        USER_EXTRA_CACHE_OBSERVER.set(observer).is_ok()
+    }
+
     pub async fn load(user_id: i32, db: &DatabaseConnection) -> Result<Self, DbErr> {
-        if let Some(user_extra) = GLOBAL_USER_EXTRA_CACHE.get(&user_id) {
+        let cached_value = GLOBAL_USER_EXTRA_CACHE.get(&user_id);
+        if let Some(observer) = USER_EXTRA_CACHE_OBSERVER.get() {
+            observer(cached_value.is_some());
+        }
+        if let Some(user_extra) = cached_value {
             return Ok(user_extra);
         }
 
@@ -126,6 +174,15 @@ impl Userextra {
 
         Ok(user_extra)
     }
+
+    /// 用户的成就发生变化后调用，使下一次加载时重新查询数据库。
+    pub async fn invalidate(user_id: i32) {
+        GLOBAL_USER_EXTRA_CACHE.invalidate(&user_id).await;
+    }
+
+    pub fn invalidate_all() {
+        GLOBAL_USER_EXTRA_CACHE.invalidate_all();
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
@@ -182,7 +239,7 @@ impl From<Model> for HistoryReview {
 }
 
 impl GetMyReview {
-    pub fn new(model: Model, course: course::Model, group_id: i32, user_id: i32) -> Self {
+    pub fn new(model: Model, course: course::GetMultiCourse, group_id: i32, user_id: i32) -> Self {
         let (upvote, downvote, voted) = _calculate_votes(&model, user_id);
         GetMyReview {
             id: model.id,
@@ -205,13 +262,14 @@ pub struct GetMyReview {
     pub id: i32,
     pub title: String,
     pub content: String,
+    /// 对非管理员，`alter_by` 和 `original.reviewer_id` 为评论者在本课程下的假名
     pub history: Json,
     pub time_created: DateTime,
     pub time_updated: DateTime,
     pub rank: Json,
     pub vote: i32,
     pub remark: i32,
-    pub course: course::Model,
+    pub course: course::GetMultiCourse,
     pub group_id: i32,
 }
 
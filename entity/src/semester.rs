@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use sea_orm::entity::prelude::*;
+use sea_orm::ActiveValue::{NotSet, Set};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 学期。课程的 `year` 和 `semester` 对应其中的一行
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, DeriveEntityModel)]
+#[sea_orm(table_name = "semester")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    /// 学年的开始年份，如 2021 表示 2021-2022 学年
+    pub year: i32,
+    /// 学年中的编号，如 1 为秋季学期，2 为寒假，3 为春季学期，4 为暑假
+    pub semester: i32,
+    /// 迁移时为已有课程的学期创建的记录没有名称和日期
+    #[sea_orm(column_type = "Text")]
+    pub name: Option<String>,
+    pub start_date: Option<Date>,
+    pub end_date: Option<Date>,
+    /// 至多有一个学期为当前学期
+    pub is_current: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl From<Model> for GetSemester {
+    fn from(model: Model) -> Self {
+        GetSemester {
+            id: model.id,
+            year: model.year,
+            semester: model.semester,
+            name: model.name,
+            start_date: model.start_date,
+            end_date: model.end_date,
+            is_current: model.is_current,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct GetSemester {
+    pub id: i32,
+    pub year: i32,
+    pub semester: i32,
+    pub name: Option<String>,
+    #[schema(value_type = Option<String>, format = Date)]
+    pub start_date: Option<Date>,
+    #[schema(value_type = Option<String>, format = Date)]
+    pub end_date: Option<Date>,
+    pub is_current: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct NewSemester {
+    pub year: i32,
+    pub semester: i32,
+    /// 显示的名称，如“2021-2022 学年秋季学期”。不填时课程中不显示学期名称
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>, format = Date)]
+    pub start_date: Option<Date>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>, format = Date)]
+    pub end_date: Option<Date>,
+    /// 设为当前学期时，其他学期不再是当前学期
+    #[serde(default)]
+    pub is_current: bool,
+}
+
+impl NewSemester {
+    pub fn into_active_model(self) -> ActiveModel {
+        ActiveModel {
+            id: NotSet,
+            year: Set(self.year),
+            semester: Set(self.semester),
+            name: Set(self.name),
+            start_date: Set(self.start_date),
+            end_date: Set(self.end_date),
+            is_current: Set(self.is_current),
+        }
+    }
+}
+
+impl ActiveModel {
+    pub fn update_with(&mut self, updated_semester: NewSemester) {
+        self.year = Set(updated_semester.year);
+        self.semester = Set(updated_semester.semester);
+        self.name = Set(updated_semester.name);
+        self.start_date = Set(updated_semester.start_date);
+        self.end_date = Set(updated_semester.end_date);
+        self.is_current = Set(updated_semester.is_current);
+    }
+}
+
+/// 以学年和学期编号为键的学期名称，用于在课程中显示所在学期的名称
+#[derive(Debug, Clone, Default)]
+pub struct SemesterNames(HashMap<(i32, i32), String>);
+
+impl SemesterNames {
+    pub async fn load<C: ConnectionTrait>(db: &C) -> Result<Self, DbErr> {
+        let semesters = Entity::find().all(db).await?;
+        Ok(SemesterNames(
+            semesters
+                .into_iter()
+                .filter_map(|semester| Some(((semester.year, semester.semester), semester.name?)))
+                .collect(),
+        ))
+    }
+
+    /// 未登记或未填写名称的学期没有名称
+    pub fn get(&self, year: i32, semester: i32) -> Option<String> {
+        self.0.get(&(year, semester)).cloned()
+    }
+}
@@ -47,6 +47,8 @@ impl GetAchievement {
                 ache.map(|ache| GetAchievement {
                     name: ache.name,
                     domain: ache.domain,
+                    description: ache.description,
+                    icon: ache.icon,
                     obtain_date: model.obtain_date,
                 })
             })
@@ -58,5 +60,7 @@ impl GetAchievement {
 pub struct GetAchievement {
     name: String,
     domain: Option<String>,
+    description: Option<String>,
+    icon: Option<String>,
     obtain_date: DateTime,
 }
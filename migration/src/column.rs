@@ -0,0 +1,12 @@
+use crate::sea_orm::DbBackend;
+use sea_orm_migration::prelude::*;
+
+/// 长文本列。MySQL 的 TEXT 最长只有 64KB，因此沿用 LONGTEXT；SQLite 和 Postgres 的 TEXT 没有长度限制
+pub(crate) fn long_text<T: IntoIden>(backend: DbBackend, name: T) -> ColumnDef {
+    let mut column = ColumnDef::new(name);
+    match backend {
+        DbBackend::MySql => column.custom(Alias::new("LONGTEXT")),
+        DbBackend::Postgres | DbBackend::Sqlite => column.text(),
+    };
+    column
+}
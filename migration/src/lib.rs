@@ -1,7 +1,13 @@
+mod column;
 mod m20220826_174342_create_table;
 mod m20230119_125830_create_user_extra;
 mod m20230214_202755_related_to_foreign;
 mod m20230215_111344_userextra_to_achievement;
+mod m20261019_000001_achievement_description;
+mod m20261019_000002_achievement_unique;
+mod m20261019_000003_erasure_audit;
+mod m20261019_000004_semester;
+mod tests;
 
 pub use sea_orm_migration::prelude::*;
 
@@ -15,6 +21,10 @@ impl MigratorTrait for Migrator {
             Box::new(m20230119_125830_create_user_extra::Migration),
             Box::new(m20230214_202755_related_to_foreign::Migration),
             Box::new(m20230215_111344_userextra_to_achievement::Migration),
+            Box::new(m20261019_000001_achievement_description::Migration),
+            Box::new(m20261019_000002_achievement_unique::Migration),
+            Box::new(m20261019_000003_erasure_audit::Migration),
+            Box::new(m20261019_000004_semester::Migration),
         ]
     }
 }
@@ -1,4 +1,5 @@
-use crate::sea_orm::{ConnectionTrait, TransactionTrait};
+use crate::column::long_text;
+use crate::sea_orm::{ConnectionTrait, DbBackend, TransactionTrait};
 use sea_orm_migration::prelude::*;
 
 pub struct Migration;
@@ -9,7 +10,7 @@ impl MigrationName for Migration {
     }
 }
 
-fn course() -> TableCreateStatement {
+fn course(backend: DbBackend) -> TableCreateStatement {
     Table::create()
         .table(Alias::new("course"))
         .col(
@@ -20,34 +21,28 @@ fn course() -> TableCreateStatement {
                 .primary_key(),
         )
         .col(
-            ColumnDef::new(Alias::new("name"))
-                .custom(Alias::new("LONGTEXT"))
+            long_text(backend, Alias::new("name"))
                 .not_null(),
         )
         .col(
-            ColumnDef::new(Alias::new("code"))
-                .custom(Alias::new("LONGTEXT"))
+            long_text(backend, Alias::new("code"))
                 .not_null(),
         )
         .col(
-            ColumnDef::new(Alias::new("code_id"))
-                .custom(Alias::new("LONGTEXT"))
+            long_text(backend, Alias::new("code_id"))
                 .not_null(),
         )
         .col(ColumnDef::new(Alias::new("credit")).double().not_null())
         .col(
-            ColumnDef::new(Alias::new("department"))
-                .custom(Alias::new("LONGTEXT"))
+            long_text(backend, Alias::new("department"))
                 .not_null(),
         )
         .col(
-            ColumnDef::new(Alias::new("campus_name"))
-                .custom(Alias::new("LONGTEXT"))
+            long_text(backend, Alias::new("campus_name"))
                 .not_null(),
         )
         .col(
-            ColumnDef::new(Alias::new("teachers"))
-                .custom(Alias::new("LONGTEXT"))
+            long_text(backend, Alias::new("teachers"))
                 .not_null(),
         )
         .col(
@@ -69,7 +64,7 @@ fn course_review() -> TableCreateStatement {
         .to_owned()
 }
 
-fn coursegroup() -> TableCreateStatement {
+fn coursegroup(backend: DbBackend) -> TableCreateStatement {
     Table::create()
         .table(Alias::new("coursegroup"))
         .col(
@@ -80,23 +75,19 @@ fn coursegroup() -> TableCreateStatement {
                 .primary_key(),
         )
         .col(
-            ColumnDef::new(Alias::new("name"))
-                .custom(Alias::new("LONGTEXT"))
+            long_text(backend, Alias::new("name"))
                 .not_null(),
         )
         .col(
-            ColumnDef::new(Alias::new("code"))
-                .custom(Alias::new("LONGTEXT"))
+            long_text(backend, Alias::new("code"))
                 .not_null(),
         )
         .col(
-            ColumnDef::new(Alias::new("department"))
-                .custom(Alias::new("LONGTEXT"))
+            long_text(backend, Alias::new("department"))
                 .not_null(),
         )
         .col(
-            ColumnDef::new(Alias::new("campus_name"))
-                .custom(Alias::new("LONGTEXT"))
+            long_text(backend, Alias::new("campus_name"))
                 .not_null(),
         )
         .to_owned()
@@ -114,7 +105,7 @@ fn coursegroup_course() -> TableCreateStatement {
         .to_owned()
 }
 
-fn review() -> TableCreateStatement {
+fn review(backend: DbBackend) -> TableCreateStatement {
     Table::create()
         .table(Alias::new("review"))
         .col(
@@ -125,16 +116,14 @@ fn review() -> TableCreateStatement {
                 .primary_key(),
         )
         .col(
-            ColumnDef::new(Alias::new("title"))
-                .custom(Alias::new("LONGTEXT"))
+            long_text(backend, Alias::new("title"))
                 .not_null(),
         )
         .col(
-            ColumnDef::new(Alias::new("content"))
-                .custom(Alias::new("LONGTEXT"))
+            long_text(backend, Alias::new("content"))
                 .not_null(),
         )
-        .col(ColumnDef::new(Alias::new("history")).json().not_null())
+        .col(ColumnDef::new(Alias::new("history")).json_binary().not_null())
         .col(
             ColumnDef::new(Alias::new("reviewer_id"))
                 .integer()
@@ -150,9 +139,9 @@ fn review() -> TableCreateStatement {
                 .date_time()
                 .not_null(),
         )
-        .col(ColumnDef::new(Alias::new("rank")).json().not_null())
-        .col(ColumnDef::new(Alias::new("upvoters")).json().not_null())
-        .col(ColumnDef::new(Alias::new("downvoters")).json().not_null())
+        .col(ColumnDef::new(Alias::new("rank")).json_binary().not_null())
+        .col(ColumnDef::new(Alias::new("upvoters")).json_binary().not_null())
+        .col(ColumnDef::new(Alias::new("downvoters")).json_binary().not_null())
         .to_owned()
 }
 
@@ -163,11 +152,11 @@ impl MigrationTrait for Migration {
         let backend = manager.get_database_backend();
         let transaction = db.begin().await?;
         for mut table in [
-            course(),
+            course(backend),
             course_review(),
-            coursegroup(),
+            coursegroup(backend),
             coursegroup_course(),
-            review(),
+            review(backend),
         ] {
             transaction
                 .execute(backend.build(table.if_not_exists()))
@@ -176,7 +165,22 @@ impl MigrationTrait for Migration {
         transaction.commit().await
     }
 
-    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
-        todo!()
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let transaction = db.begin().await?;
+        // SQLite 不支持在一条 DROP TABLE 语句中删除多个表，因此逐表执行
+        for table in [
+            "review",
+            "coursegroup_course",
+            "coursegroup",
+            "course_review",
+            "course",
+        ] {
+            transaction
+                .execute(backend.build(Table::drop().table(Alias::new(table))))
+                .await?;
+        }
+        transaction.commit().await
     }
 }
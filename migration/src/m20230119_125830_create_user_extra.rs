@@ -1,3 +1,5 @@
+use crate::column::long_text;
+use crate::sea_orm::DbBackend;
 use sea_orm_migration::prelude::*;
 
 pub struct Migration;
@@ -8,7 +10,7 @@ impl MigrationName for Migration {
     }
 }
 
-fn userextra() -> TableCreateStatement {
+fn userextra(backend: DbBackend) -> TableCreateStatement {
     Table::create()
         .table(Alias::new("userextra"))
         .col(
@@ -19,8 +21,7 @@ fn userextra() -> TableCreateStatement {
                 .primary_key(),
         )
         .col(
-            ColumnDef::new(Alias::new("extra"))
-                .custom(Alias::new("LONGTEXT"))
+            long_text(backend, Alias::new("extra"))
                 .not_null(),
         )
         .to_owned()
@@ -30,11 +31,13 @@ fn userextra() -> TableCreateStatement {
 impl MigrationTrait for Migration {
     async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
         manager
-            .create_table(userextra().if_not_exists().to_owned())
+            .create_table(userextra(manager.get_database_backend()).if_not_exists().to_owned())
             .await
     }
 
-    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
-        todo!()
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Alias::new("userextra")).to_owned())
+            .await
     }
 }
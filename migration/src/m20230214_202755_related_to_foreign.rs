@@ -1,4 +1,4 @@
-use crate::sea_orm::{ConnectionTrait, DatabaseTransaction, DbBackend, TransactionTrait};
+use crate::sea_orm::{ConnectionTrait, DatabaseTransaction, DbBackend, Statement, TransactionTrait};
 use sea_orm_migration::prelude::*;
 use std::borrow::Borrow;
 
@@ -10,21 +10,6 @@ impl MigrationName for Migration {
     }
 }
 
-fn from_related_to_foreign_key(
-    child_table: &str,
-    related_table: &str,
-    child_key_col: &str,
-    child_foreign_col: &str,
-    related_child_key_col: &str,
-    related_parent_key_col: &str,
-) -> String {
-    format!(
-        "UPDATE {child_table}
-    LEFT JOIN {related_table} AS cc ON {child_table}.{child_key_col} = cc.{related_child_key_col}
-    SET {child_table}.{child_foreign_col} = cc.{related_parent_key_col}"
-    )
-}
-
 struct ForeignKeyMigration {
     child_tbl: String,
     related_tbl: String,
@@ -40,6 +25,70 @@ struct ForeignKeyMigration {
 }
 
 impl ForeignKeyMigration {
+    /// 用关联表填充子表外键列的语句。
+    /// 使用相关子查询而非 `UPDATE ... JOIN`，后者在 SQLite 和 Postgres 中不可用
+    fn fill_foreign_key(&self) -> UpdateStatement {
+        let related_parent_key = Query::select()
+            .column(Alias::new(self.related_parent_key_col.as_str()))
+            .from(Alias::new(self.related_tbl.as_str()))
+            .and_where(
+                Expr::col((
+                    Alias::new(self.related_tbl.as_str()),
+                    Alias::new(self.related_child_key_col.as_str()),
+                ))
+                    .equals((
+                        Alias::new(self.child_tbl.as_str()),
+                        Alias::new(self.child_key_col.as_str()),
+                    )),
+            )
+            .limit(1)
+            .to_owned();
+        Query::update()
+            .table(Alias::new(self.child_tbl.as_str()))
+            .value(
+                Alias::new(self.child_foreign_col.as_str()),
+                SimpleExpr::SubQuery(None, Box::new(related_parent_key.into_sub_query_statement())),
+            )
+            .to_owned()
+    }
+
+    /// 用子表的外键列重建关联表的语句
+    fn fill_related(&self) -> Result<InsertStatement, DbErr> {
+        let foreign_keys = Query::select()
+            .columns([
+                Alias::new(self.child_foreign_col.as_str()),
+                Alias::new(self.child_key_col.as_str()),
+            ])
+            .from(Alias::new(self.child_tbl.as_str()))
+            .and_where(Expr::col(Alias::new(self.child_foreign_col.as_str())).is_not_null())
+            .to_owned();
+        Ok(Query::insert()
+            .into_table(Alias::new(self.related_tbl.as_str()))
+            .columns([
+                Alias::new(self.related_parent_key_col.as_str()),
+                Alias::new(self.related_child_key_col.as_str()),
+            ])
+            .select_from(foreign_keys)
+            .map_err(|e| DbErr::Custom(e.to_string()))?
+            .to_owned())
+    }
+
+    fn related_table(&self) -> TableCreateStatement {
+        Table::create()
+            .table(Alias::new(self.related_tbl.as_str()))
+            .col(
+                ColumnDef::new(Alias::new(self.related_parent_key_col.as_str()))
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(Alias::new(self.related_child_key_col.as_str()))
+                    .integer()
+                    .not_null(),
+            )
+            .to_owned()
+    }
+
     async fn migrate<T: ConnectionTrait>(
         &self,
         backend: impl Borrow<DbBackend>,
@@ -47,7 +96,7 @@ impl ForeignKeyMigration {
     ) -> Result<(), DbErr> {
         let conn = conn.borrow();
         let backend = backend.borrow();
-        // 在子表中添加外键列
+        // 在子表中添加外键列。SQLite 不支持向已有的表添加外键约束，只会添加列
         let sql = Table::alter()
             .table(Alias::new(self.child_tbl.as_str()))
             .add_column(
@@ -68,18 +117,8 @@ impl ForeignKeyMigration {
 
         conn.execute(backend.build(&sql)).await?;
 
-        // 连接子表和关联表，将子表的外键列填充
-        conn.execute_unprepared(
-            from_related_to_foreign_key(
-                self.child_tbl.as_str(),
-                self.related_tbl.as_str(),
-                self.child_key_col.as_str(),
-                self.child_foreign_col.as_str(),
-                self.related_child_key_col.as_str(),
-                self.related_parent_key_col.as_str(),
-            )
-                .as_str(),
-        )
+        // 根据关联表，将子表的外键列填充
+        conn.execute(backend.build(&self.fill_foreign_key()))
             .await?;
 
         // 删除关联表
@@ -90,6 +129,79 @@ impl ForeignKeyMigration {
 
         Ok(())
     }
+
+    async fn rollback<T: ConnectionTrait>(
+        &self,
+        backend: impl Borrow<DbBackend>,
+        conn: impl Borrow<T>,
+    ) -> Result<(), DbErr> {
+        let conn = conn.borrow();
+        let backend = backend.borrow();
+        // 重建关联表，并用子表的外键列填充
+        conn.execute(backend.build(&self.related_table())).await?;
+        conn.execute(backend.build(&self.fill_related()?))
+            .await?;
+
+        // MySQL 不允许删除带有外键约束的列，需先删除约束。
+        // 约束是在添加列时自动命名的，只能从 information_schema 中查出
+        if *backend == DbBackend::MySql {
+            let constraints = conn
+                .query_all(Statement::from_sql_and_values(
+                    *backend,
+                    "SELECT CONSTRAINT_NAME AS name FROM information_schema.KEY_COLUMN_USAGE \
+                    WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME = ? AND COLUMN_NAME = ? \
+                    AND REFERENCED_TABLE_NAME IS NOT NULL",
+                    [
+                        self.child_tbl.as_str().into(),
+                        self.child_foreign_col.as_str().into(),
+                    ],
+                ))
+                .await?;
+            for constraint in constraints {
+                let name: String = constraint.try_get("", "name")?;
+                let sql = ForeignKey::drop()
+                    .name(name.as_str())
+                    .table(Alias::new(self.child_tbl.as_str()))
+                    .to_owned();
+                conn.execute(backend.build(&sql)).await?;
+            }
+        }
+
+        // 删除子表的外键列。Postgres 会一并删除列上的外键约束
+        let sql = Table::alter()
+            .table(Alias::new(self.child_tbl.as_str()))
+            .drop_column(Alias::new(self.child_foreign_col.as_str()))
+            .to_owned();
+        conn.execute(backend.build(&sql)).await?;
+
+        Ok(())
+    }
+}
+
+fn course_group_course() -> ForeignKeyMigration {
+    ForeignKeyMigration {
+        child_tbl: "course".to_string(),
+        related_tbl: "coursegroup_course".to_string(),
+        parent_tbl: "coursegroup".to_string(),
+        child_key_col: "id".to_string(),
+        parent_key_col: "id".to_string(),
+        child_foreign_col: "coursegroup_id".to_string(),
+        related_child_key_col: "course_id".to_string(),
+        related_parent_key_col: "coursegroup_id".to_string(),
+    }
+}
+
+fn course_review() -> ForeignKeyMigration {
+    ForeignKeyMigration {
+        child_tbl: "review".to_string(),
+        related_tbl: "course_review".to_string(),
+        parent_tbl: "course".to_string(),
+        child_key_col: "id".to_string(),
+        parent_key_col: "id".to_string(),
+        child_foreign_col: "course_id".to_string(),
+        related_child_key_col: "review_id".to_string(),
+        related_parent_key_col: "course_id".to_string(),
+    }
 }
 
 #[async_trait::async_trait]
@@ -99,38 +211,29 @@ impl MigrationTrait for Migration {
         let transaction = db.begin().await?;
         let backend = manager.get_database_backend();
 
-        let course_group_course = ForeignKeyMigration {
-            child_tbl: "course".to_string(),
-            related_tbl: "coursegroup_course".to_string(),
-            parent_tbl: "coursegroup".to_string(),
-            child_key_col: "id".to_string(),
-            parent_key_col: "id".to_string(),
-            child_foreign_col: "coursegroup_id".to_string(),
-            related_child_key_col: "course_id".to_string(),
-            related_parent_key_col: "coursegroup_id".to_string(),
-        };
-        course_group_course
+        course_group_course()
             .migrate::<DatabaseTransaction>(&backend, &transaction)
             .await?;
-
-        let course_review = ForeignKeyMigration {
-            child_tbl: "review".to_string(),
-            related_tbl: "course_review".to_string(),
-            parent_tbl: "course".to_string(),
-            child_key_col: "id".to_string(),
-            parent_key_col: "id".to_string(),
-            child_foreign_col: "course_id".to_string(),
-            related_child_key_col: "review_id".to_string(),
-            related_parent_key_col: "course_id".to_string(),
-        };
-        course_review
+        course_review()
             .migrate::<DatabaseTransaction>(&backend, &transaction)
             .await?;
 
         transaction.commit().await
     }
 
-    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
-        todo!()
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let transaction = db.begin().await?;
+        let backend = manager.get_database_backend();
+
+        // 与 up 的顺序相反
+        course_review()
+            .rollback::<DatabaseTransaction>(&backend, &transaction)
+            .await?;
+        course_group_course()
+            .rollback::<DatabaseTransaction>(&backend, &transaction)
+            .await?;
+
+        transaction.commit().await
     }
 }
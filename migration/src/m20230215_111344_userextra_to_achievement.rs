@@ -1,5 +1,8 @@
-use crate::sea_orm::{ConnectionTrait, TransactionTrait};
+use crate::column::long_text;
+use crate::sea_orm::{ConnectionTrait, DbBackend, TransactionTrait};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
 use sea_orm_migration::prelude::*;
+use std::collections::HashMap;
 
 pub struct Migration;
 
@@ -9,7 +12,7 @@ impl MigrationName for Migration {
     }
 }
 
-fn achievement() -> TableCreateStatement {
+fn achievement(backend: DbBackend) -> TableCreateStatement {
     Table::create()
         .table(Alias::new("achievement"))
         .col(
@@ -20,11 +23,10 @@ fn achievement() -> TableCreateStatement {
                 .primary_key(),
         )
         .col(
-            ColumnDef::new(Alias::new("name"))
-                .custom(Alias::new("LONGTEXT"))
+            long_text(backend, Alias::new("name"))
                 .not_null(),
         )
-        .col(ColumnDef::new(Alias::new("domain")).custom(Alias::new("LONGTEXT")))
+        .col(&mut long_text(backend, Alias::new("domain")))
         .to_owned()
 }
 
@@ -45,36 +47,64 @@ fn user_achievement() -> TableCreateStatement {
         .to_owned()
 }
 
-const GENERATE_ACHIEVEMENTS: &str = r#"
-INSERT INTO achievement ( name, domain ) 
-SELECT DISTINCT ach_name, ach_domain 
-FROM userextra
-CROSS JOIN JSON_TABLE (
-	userextra.extra,
-	'$.achievements[*]' COLUMNS ( ach_name LONGTEXT PATH '$.name' ERROR ON ERROR, ach_domain LONGTEXT PATH '$.domain' ERROR ON ERROR )) AS jt
-"#;
-
-const MIGRATE_USER_ACHIEVEMENTS: &str = r#"
-INSERT INTO user_achievement ( user_id, achievement_id, obtain_date )
-SELECT
-	userextra.user_id,
-	(
-	SELECT
-		id 
-	FROM
-		achievement 
-	WHERE
-        BINARY achievement.`name` = BINARY ach_name 
-		AND (
-			BINARY achievement.domain = BINARY ach_domain 
-		OR ( achievement.domain IS NULL AND ach_domain IS NULL ))),
-	CAST( ach_date AS datetime ) 
-FROM
-	userextra
-CROSS JOIN JSON_TABLE (
-	userextra.extra,
-	'$.achievements[*]' COLUMNS ( ach_name LONGTEXT PATH '$.name' ERROR ON ERROR, ach_domain LONGTEXT PATH '$.domain' ERROR ON ERROR, ach_date LONGTEXT PATH '$.obtain_date' ERROR ON ERROR )) AS jt
-"#;
+/// userextra 的 `extra` 列中保存的一个成就
+struct LegacyAchievement {
+    user_id: i32,
+    name: String,
+    domain: Option<String>,
+    obtain_date: NaiveDateTime,
+}
+
+fn invalid_extra(user_id: i32, reason: impl std::fmt::Display) -> DbErr {
+    DbErr::Custom(format!(
+        "Invalid achievements in userextra of user {}: {}",
+        user_id, reason
+    ))
+}
+
+/// 按 MySQL `CAST(... AS datetime)` 能接受的常见格式解析成就的获得时间。带时区的时间取其本地时间
+fn parse_obtain_date(date: &str) -> Option<NaiveDateTime> {
+    date.parse::<NaiveDateTime>()
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S%.f").ok())
+        .or_else(|| DateTime::parse_from_rfc3339(date).ok().map(|date| date.naive_local()))
+        .or_else(|| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+/// 解析 `extra` 中的 `achievements` 数组。没有该字段视为没有成就
+fn parse_legacy_achievements(user_id: i32, extra: &str) -> Result<Vec<LegacyAchievement>, DbErr> {
+    let extra: serde_json::Value =
+        serde_json::from_str(extra).map_err(|e| invalid_extra(user_id, e))?;
+    let Some(achievements) = extra.get("achievements") else {
+        return Ok(vec![]);
+    };
+    let achievements = achievements
+        .as_array()
+        .ok_or_else(|| invalid_extra(user_id, "`achievements` is not an array"))?;
+    achievements
+        .iter()
+        .map(|achievement| {
+            let name = achievement["name"]
+                .as_str()
+                .ok_or_else(|| invalid_extra(user_id, "missing `name`"))?;
+            let obtain_date = achievement["obtain_date"]
+                .as_str()
+                .ok_or_else(|| invalid_extra(user_id, "missing `obtain_date`"))?;
+            Ok(LegacyAchievement {
+                user_id,
+                name: name.to_string(),
+                domain: achievement["domain"].as_str().map(str::to_string),
+                obtain_date: parse_obtain_date(obtain_date).ok_or_else(|| {
+                    invalid_extra(user_id, format!("invalid `obtain_date` {}", obtain_date))
+                })?,
+            })
+        })
+        .collect()
+}
 
 #[async_trait::async_trait]
 impl MigrationTrait for Migration {
@@ -85,27 +115,102 @@ impl MigrationTrait for Migration {
 
         // 创建新表
         transaction
-            .execute(backend.build(achievement().if_not_exists()))
+            .execute(backend.build(achievement(backend).if_not_exists()))
             .await?;
 
         transaction
             .execute(backend.build(user_achievement().if_not_exists()))
             .await?;
 
-        // 向 acheivement 表中插入所有已有的成就
-        transaction
-            .execute_unprepared(GENERATE_ACHIEVEMENTS)
+        // 读取 userextra 表中的成就信息。各数据库的 JSON 函数互不兼容，因此在程序中解析
+        let rows = transaction
+            .query_all(
+                backend.build(
+                    Query::select()
+                        .columns([Alias::new("user_id"), Alias::new("extra")])
+                        .from(Alias::new("userextra"))
+                        .order_by(Alias::new("user_id"), Order::Asc),
+                ),
+            )
             .await?;
+        let mut legacy_achievements = vec![];
+        for row in rows {
+            let user_id: i32 = row.try_get("", "user_id")?;
+            let extra: String = row.try_get("", "extra")?;
+            legacy_achievements.extend(parse_legacy_achievements(user_id, &extra)?);
+        }
+        if legacy_achievements.is_empty() {
+            return transaction.commit().await;
+        }
 
-        // 将 userextra 表中的成就信息转移到 user_achievement 表中
-        transaction
-            .execute_unprepared(MIGRATE_USER_ACHIEVEMENTS)
+        // 向 acheivement 表中插入所有已有的成就，名称和领域均相同的视为同一成就
+        let mut distinct: Vec<(&str, Option<&str>)> = vec![];
+        for achievement in &legacy_achievements {
+            let key = (achievement.name.as_str(), achievement.domain.as_deref());
+            if !distinct.contains(&key) {
+                distinct.push(key);
+            }
+        }
+        let mut insert = Query::insert()
+            .into_table(Alias::new("achievement"))
+            .columns([Alias::new("name"), Alias::new("domain")])
+            .to_owned();
+        for (name, domain) in &distinct {
+            insert.values_panic([(*name).into(), (*domain).into()]);
+        }
+        transaction.execute(backend.build(&insert)).await?;
+
+        let rows = transaction
+            .query_all(
+                backend.build(
+                    Query::select()
+                        .columns([Alias::new("id"), Alias::new("name"), Alias::new("domain")])
+                        .from(Alias::new("achievement"))
+                        .order_by(Alias::new("id"), Order::Asc),
+                ),
+            )
             .await?;
+        let mut achievement_ids: HashMap<(String, Option<String>), i32> = HashMap::new();
+        for row in rows {
+            achievement_ids
+                .entry((row.try_get("", "name")?, row.try_get("", "domain")?))
+                .or_insert(row.try_get("", "id")?);
+        }
+
+        // 将 userextra 表中的成就信息转移到 user_achievement 表中
+        let mut insert = Query::insert()
+            .into_table(Alias::new("user_achievement"))
+            .columns([
+                Alias::new("user_id"),
+                Alias::new("achievement_id"),
+                Alias::new("obtain_date"),
+            ])
+            .to_owned();
+        for achievement in legacy_achievements {
+            let achievement_id = achievement_ids[&(achievement.name, achievement.domain)];
+            insert.values_panic([
+                achievement.user_id.into(),
+                achievement_id.into(),
+                achievement.obtain_date.into(),
+            ]);
+        }
+        transaction.execute(backend.build(&insert)).await?;
 
         transaction.commit().await
     }
 
-    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
-        todo!()
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let transaction = db.begin().await?;
+
+        // 成就信息仍保留在 userextra 表中，删除新表即可
+        for table in ["user_achievement", "achievement"] {
+            transaction
+                .execute(backend.build(Table::drop().table(Alias::new(table))))
+                .await?;
+        }
+
+        transaction.commit().await
     }
 }
@@ -0,0 +1,45 @@
+use crate::column::long_text;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261019_000001_achievement_description"
+    }
+}
+
+// SQLite 不支持在一条 ALTER TABLE 语句中修改多列，因此逐列执行
+const NEW_COLUMNS: [&str; 2] = ["description", "icon"];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let backend = manager.get_database_backend();
+        for column in NEW_COLUMNS {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Alias::new("achievement"))
+                        .add_column(&mut long_text(backend, Alias::new(column)))
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in NEW_COLUMNS {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Alias::new("achievement"))
+                        .drop_column(Alias::new(column))
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+use crate::column::long_text;
+use crate::sea_orm::prelude::DateTime;
+use crate::sea_orm::{ConnectionTrait, DbBackend, Statement, TransactionTrait};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261019_000002_achievement_unique"
+    }
+}
+
+const ACHIEVEMENT_NAME_INDEX: &str = "idx-achievement-name-domain";
+const USER_ACHIEVEMENT_INDEX: &str = "idx-user_achievement-user-achievement";
+
+/// MySQL 不能为 LONGTEXT 列建立完整的索引，只按前缀建立索引又会使前缀相同的名称冲突，
+/// 因此将名称和领域改为 VARCHAR。两列合计不超过 InnoDB 索引长度的上限
+const MYSQL_NAME_LENGTH: u32 = 255;
+
+fn achievement_name_index() -> IndexCreateStatement {
+    Index::create()
+        .name(ACHIEVEMENT_NAME_INDEX)
+        .table(Alias::new("achievement"))
+        .col(Alias::new("name"))
+        .col(Alias::new("domain"))
+        .unique()
+        .to_owned()
+}
+
+fn user_achievement_index() -> IndexCreateStatement {
+    Index::create()
+        .name(USER_ACHIEVEMENT_INDEX)
+        .table(Alias::new("user_achievement"))
+        .col(Alias::new("user_id"))
+        .col(Alias::new("achievement_id"))
+        .unique()
+        .to_owned()
+}
+
+/// MySQL 中名称和领域列的定义，`varchar` 为 `false` 时恢复为 LONGTEXT
+fn mysql_name_columns(varchar: bool) -> TableAlterStatement {
+    let column = |name: &str| {
+        if varchar {
+            ColumnDef::new(Alias::new(name)).string_len(MYSQL_NAME_LENGTH).to_owned()
+        } else {
+            long_text(DbBackend::MySql, Alias::new(name))
+        }
+    };
+    Table::alter()
+        .table(Alias::new("achievement"))
+        .modify_column(column("name").not_null())
+        .modify_column(column("domain").null())
+        .to_owned()
+}
+
+/// 名称和领域与给定值相同的成就。比较由数据库完成，与唯一索引使用相同的排序规则
+fn same_achievement(name: String, domain: Option<String>) -> Condition {
+    Condition::all()
+        .add(Expr::col(Alias::new("name")).eq(name))
+        .add(match domain {
+            Some(domain) => Expr::col(Alias::new("domain")).eq(domain),
+            None => Expr::col(Alias::new("domain")).is_null(),
+        })
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+
+        if backend == DbBackend::MySql {
+            let rows = db
+                .query_all(Statement::from_string(
+                    backend,
+                    format!(
+                        "SELECT id FROM achievement WHERE CHAR_LENGTH(name) > {0} OR CHAR_LENGTH(domain) > {0}",
+                        MYSQL_NAME_LENGTH
+                    ),
+                ))
+                .await?;
+            if !rows.is_empty() {
+                let ids = rows
+                    .iter()
+                    .map(|row| row.try_get::<i32>("", "id").map(|id| id.to_string()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                return Err(DbErr::Migration(format!(
+                    "the name or domain of achievements {} is longer than {} characters, please shorten them first",
+                    ids.join(", "),
+                    MYSQL_NAME_LENGTH
+                )));
+            }
+            manager.alter_table(mysql_name_columns(true)).await?;
+        }
+
+        let transaction = db.begin().await?;
+
+        // 合并名称和领域都相同的成就，保留 id 最小的一个。分组由数据库完成，
+        // 因此 MySQL 默认排序规则下只有大小写不同的名称也会被合并，与唯一索引判断重复的方式一致
+        let groups = transaction
+            .query_all(
+                backend.build(
+                    Query::select()
+                        .expr_as(Expr::col(Alias::new("id")).min(), Alias::new("kept_id"))
+                        .columns([Alias::new("name"), Alias::new("domain")])
+                        .from(Alias::new("achievement"))
+                        .group_by_columns([Alias::new("name"), Alias::new("domain")])
+                        .and_having(Expr::expr(Expr::col(Alias::new("id")).count()).gt(1)),
+                ),
+            )
+            .await?;
+        for row in groups {
+            let kept_id: i32 = row.try_get("", "kept_id")?;
+            let duplicates = same_achievement(row.try_get("", "name")?, row.try_get("", "domain")?)
+                .add(Expr::col(Alias::new("id")).ne(kept_id));
+            transaction
+                .execute(
+                    backend.build(
+                        Query::update()
+                            .table(Alias::new("user_achievement"))
+                            .value(Alias::new("achievement_id"), kept_id)
+                            .and_where(
+                                Expr::col(Alias::new("achievement_id")).in_subquery(
+                                    Query::select()
+                                        .column(Alias::new("id"))
+                                        .from(Alias::new("achievement"))
+                                        .cond_where(duplicates.clone())
+                                        .to_owned(),
+                                ),
+                            ),
+                    ),
+                )
+                .await?;
+            transaction
+                .execute(
+                    backend.build(
+                        Query::delete()
+                            .from_table(Alias::new("achievement"))
+                            .cond_where(duplicates),
+                    ),
+                )
+                .await?;
+        }
+
+        // 同一用户重复获得的成就只保留最早的一条
+        let rows = transaction
+            .query_all(
+                backend.build(
+                    Query::select()
+                        .columns([Alias::new("user_id"), Alias::new("achievement_id")])
+                        .expr_as(
+                            Expr::col(Alias::new("obtain_date")).min(),
+                            Alias::new("obtain_date"),
+                        )
+                        .from(Alias::new("user_achievement"))
+                        .group_by_columns([Alias::new("user_id"), Alias::new("achievement_id")])
+                        .and_having(Expr::expr(Expr::col(Alias::new("user_id")).count()).gt(1)),
+                ),
+            )
+            .await?;
+        for row in rows {
+            let user_id: i32 = row.try_get("", "user_id")?;
+            let achievement_id: i32 = row.try_get("", "achievement_id")?;
+            let obtain_date: DateTime = row.try_get("", "obtain_date")?;
+            transaction
+                .execute(
+                    backend.build(
+                        Query::delete()
+                            .from_table(Alias::new("user_achievement"))
+                            .and_where(Expr::col(Alias::new("user_id")).eq(user_id))
+                            .and_where(Expr::col(Alias::new("achievement_id")).eq(achievement_id)),
+                    ),
+                )
+                .await?;
+            transaction
+                .execute(
+                    backend.build(
+                        Query::insert()
+                            .into_table(Alias::new("user_achievement"))
+                            .columns([
+                                Alias::new("user_id"),
+                                Alias::new("achievement_id"),
+                                Alias::new("obtain_date"),
+                            ])
+                            .values_panic([user_id.into(), achievement_id.into(), obtain_date.into()]),
+                    ),
+                )
+                .await?;
+        }
+
+        // 唯一索引中 NULL 互不相等，没有领域的同名成就由上面的合并和插入前的查询去重
+        transaction
+            .execute(backend.build(&achievement_name_index()))
+            .await?;
+        transaction
+            .execute(backend.build(&user_achievement_index()))
+            .await?;
+        transaction.commit().await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 合并的成就无法拆分，只删除索引
+        for (name, table) in [
+            (USER_ACHIEVEMENT_INDEX, "user_achievement"),
+            (ACHIEVEMENT_NAME_INDEX, "achievement"),
+        ] {
+            manager
+                .drop_index(Index::drop().name(name).table(Alias::new(table)).to_owned())
+                .await?;
+        }
+        if manager.get_database_backend() == DbBackend::MySql {
+            manager.alter_table(mysql_name_columns(false)).await?;
+        }
+        Ok(())
+    }
+}
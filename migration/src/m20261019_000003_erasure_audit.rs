@@ -0,0 +1,53 @@
+use crate::column::long_text;
+use crate::sea_orm::DbBackend;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261019_000003_erasure_audit"
+    }
+}
+
+fn erasure_audit(backend: DbBackend) -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("erasure_audit"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("user_id")).integer().not_null())
+        .col(ColumnDef::new(Alias::new("requested_by")).integer().not_null())
+        .col(
+            long_text(backend, Alias::new("mode"))
+                .not_null(),
+        )
+        .col(ColumnDef::new(Alias::new("reviews_affected")).integer().not_null())
+        .col(ColumnDef::new(Alias::new("votes_removed")).integer().not_null())
+        .col(ColumnDef::new(Alias::new("achievements_removed")).integer().not_null())
+        .col(
+            ColumnDef::new(Alias::new("time_created"))
+                .date_time()
+                .not_null(),
+        )
+        .to_owned()
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(erasure_audit(manager.get_database_backend()))
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Alias::new("erasure_audit")).to_owned())
+            .await
+    }
+}
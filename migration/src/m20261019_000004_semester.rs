@@ -0,0 +1,98 @@
+use crate::column::long_text;
+use crate::sea_orm::{ConnectionTrait, DbBackend, TransactionTrait};
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261019_000004_semester"
+    }
+}
+
+fn semester(backend: DbBackend) -> TableCreateStatement {
+    Table::create()
+        .table(Alias::new("semester"))
+        .col(
+            ColumnDef::new(Alias::new("id"))
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Alias::new("year")).integer().not_null())
+        .col(ColumnDef::new(Alias::new("semester")).integer().not_null())
+        // 名称由管理员填写，未填写时课程中不显示学期名称
+        .col(&mut long_text(backend, Alias::new("name")))
+        // 日期未知时为空
+        .col(ColumnDef::new(Alias::new("start_date")).date().null())
+        .col(ColumnDef::new(Alias::new("end_date")).date().null())
+        .col(
+            ColumnDef::new(Alias::new("is_current"))
+                .boolean()
+                .not_null()
+                .default(false),
+        )
+        .to_owned()
+}
+
+fn semester_index() -> IndexCreateStatement {
+    Index::create()
+        .name("idx-semester-year-semester")
+        .table(Alias::new("semester"))
+        .col(Alias::new("year"))
+        .col(Alias::new("semester"))
+        .unique()
+        .to_owned()
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let transaction = db.begin().await?;
+
+        transaction.execute(backend.build(&semester(backend))).await?;
+        transaction.execute(backend.build(&semester_index())).await?;
+
+        // 为已有课程的学期创建记录。名称和日期无法推断，留空由管理员填写
+        let rows = transaction
+            .query_all(
+                backend.build(
+                    Query::select()
+                        .distinct()
+                        .columns([Alias::new("year"), Alias::new("semester")])
+                        .from(Alias::new("course"))
+                        .order_by(Alias::new("year"), Order::Asc)
+                        .order_by(Alias::new("semester"), Order::Asc),
+                ),
+            )
+            .await?;
+        if rows.is_empty() {
+            return transaction.commit().await;
+        }
+        let mut insert = Query::insert()
+            .into_table(Alias::new("semester"))
+            .columns([
+                Alias::new("year"),
+                Alias::new("semester"),
+                Alias::new("is_current"),
+            ])
+            .to_owned();
+        for row in rows {
+            let year: i32 = row.try_get("", "year")?;
+            let semester: i32 = row.try_get("", "semester")?;
+            insert.values_panic([year.into(), semester.into(), false.into()]);
+        }
+        transaction.execute(backend.build(&insert)).await?;
+
+        transaction.commit().await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Alias::new("semester")).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,249 @@
+#[cfg(test)]
+#[allow(clippy::module_inception)]
+mod tests {
+    use crate::sea_orm::{ConnectionTrait, Database, DatabaseConnection, Statement};
+    use crate::{Migrator, MigratorTrait};
+    use sea_orm_migration::SchemaManager;
+
+    /// 测试使用的数据库，默认为内存中的 SQLite。也可以指向本地的 Postgres 实例，测试会清空其中的表
+    const ENV_TEST_DB_URL: &str = "TEST_DB_URL";
+
+    /// 创建 course、review 等表之后的迁移数量
+    const INITIAL_MIGRATIONS: u32 = 2;
+
+    /// 在旧表结构中插入数据：课程组与课程、课程与评论均通过关联表关联，成就保存在 userextra 中
+    const SEED: &str = r#"
+        INSERT INTO coursegroup (id, name, code, department, campus_name) VALUES
+            (1, 'Data Structures', 'COMP130004', 'Computer Science', 'Handan');
+        INSERT INTO course (id, name, code, code_id, credit, department, campus_name, teachers, max_student, week_hour, year, semester) VALUES
+            (1, 'Data Structures', 'COMP130004', 'COMP130004.01', 3, 'Computer Science', 'Handan', 'Alice', 100, 3, 2022, 1),
+            (2, 'Data Structures', 'COMP130004', 'COMP130004.01', 3, 'Computer Science', 'Handan', 'Bob', 100, 3, 2023, 1),
+            (3, 'Orphan', 'ORPH100001', 'ORPH100001.01', 1, 'Nowhere', 'Handan', 'Carol', 10, 1, 2023, 1);
+        INSERT INTO coursegroup_course (coursegroup_id, course_id) VALUES (1, 1), (1, 2);
+        INSERT INTO review (id, title, content, history, reviewer_id, time_created, time_updated, rank, upvoters, downvoters) VALUES
+            (1, 'Great', 'Learned a lot.', '[]', 233, '2023-01-01 00:00:00', '2023-01-01 00:00:00', '{}', '[]', '[]'),
+            (2, 'Lost', 'No course.', '[]', 233, '2023-01-01 00:00:00', '2023-01-01 00:00:00', '{}', '[]', '[]');
+        INSERT INTO course_review (course_id, review_id) VALUES (2, 1);
+        INSERT INTO userextra (user_id, extra) VALUES
+            (1, '{"achievements": [{"name": "First Review", "domain": "curriculum", "obtain_date": "2023-01-01T08:00:00"}, {"name": "Explorer", "domain": null, "obtain_date": "2023-02-01 12:00:00"}]}'),
+            (2, '{"achievements": [{"name": "First Review", "domain": "curriculum", "obtain_date": "2023-03-01T00:00:00+08:00"}]}'),
+            (3, '{}');
+    "#;
+
+    /// 连接到一个空数据库，并执行初始的迁移
+    async fn connect() -> DatabaseConnection {
+        let url = std::env::var(ENV_TEST_DB_URL).unwrap_or_else(|_| "sqlite::memory:".to_string());
+        let db = Database::connect(url).await.unwrap();
+        // 清除数据库中已有的表
+        Migrator::fresh(&db).await.unwrap();
+        Migrator::reset(&db).await.unwrap();
+        Migrator::up(&db, Some(INITIAL_MIGRATIONS)).await.unwrap();
+        db
+    }
+
+    async fn query_pairs(db: &DatabaseConnection, sql: &str) -> Vec<(i32, Option<i32>)> {
+        let rows = db
+            .query_all(Statement::from_string(db.get_database_backend(), sql.to_string()))
+            .await
+            .unwrap();
+        rows.iter()
+            .map(|row| (row.try_get_by_index(0).unwrap(), row.try_get_by_index(1).unwrap()))
+            .collect()
+    }
+
+    /// 检查迁移后的数据
+    async fn assert_migrated(db: &DatabaseConnection) {
+        let manager = SchemaManager::new(db);
+        assert!(!manager.has_table("coursegroup_course").await.unwrap());
+        assert!(!manager.has_table("course_review").await.unwrap());
+        assert_eq!(
+            query_pairs(db, "SELECT id, coursegroup_id FROM course ORDER BY id").await,
+            vec![(1, Some(1)), (2, Some(1)), (3, None)]
+        );
+        assert_eq!(
+            query_pairs(db, "SELECT id, course_id FROM review ORDER BY id").await,
+            vec![(1, Some(2)), (2, None)]
+        );
+
+        let rows = db
+            .query_all(Statement::from_string(
+                db.get_database_backend(),
+                "SELECT user_achievement.user_id, achievement.name, achievement.domain, user_achievement.obtain_date \
+                FROM user_achievement JOIN achievement ON achievement.id = user_achievement.achievement_id \
+                ORDER BY user_achievement.user_id, achievement.name".to_string(),
+            ))
+            .await
+            .unwrap();
+        let achievements: Vec<(i32, String, Option<String>, String)> = rows
+            .iter()
+            .map(|row| {
+                let obtain_date: chrono::NaiveDateTime = row.try_get("", "obtain_date").unwrap();
+                (
+                    row.try_get("", "user_id").unwrap(),
+                    row.try_get("", "name").unwrap(),
+                    row.try_get("", "domain").unwrap(),
+                    obtain_date.to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            achievements,
+            vec![
+                (1, "Explorer".to_string(), None, "2023-02-01 12:00:00".to_string()),
+                (1, "First Review".to_string(), Some("curriculum".to_string()), "2023-01-01 08:00:00".to_string()),
+                (2, "First Review".to_string(), Some("curriculum".to_string()), "2023-03-01 00:00:00".to_string()),
+            ]
+        );
+        // 相同的成就只插入一次
+        assert_eq!(query_pairs(db, "SELECT CAST(COUNT(*) AS INTEGER), NULL FROM achievement").await, vec![(2, None)]);
+
+        // 已有课程的学期都有记录，名称和日期留空
+        let rows = db
+            .query_all(Statement::from_string(
+                db.get_database_backend(),
+                "SELECT year, semester, name, start_date, end_date, is_current FROM semester ORDER BY year, semester".to_string(),
+            ))
+            .await
+            .unwrap();
+        let semesters: Vec<(i32, i32, Option<String>, bool)> = rows
+            .iter()
+            .map(|row| {
+                let start_date: Option<chrono::NaiveDate> = row.try_get("", "start_date").unwrap();
+                let end_date: Option<chrono::NaiveDate> = row.try_get("", "end_date").unwrap();
+                assert!(start_date.is_none() && end_date.is_none());
+                (
+                    row.try_get("", "year").unwrap(),
+                    row.try_get("", "semester").unwrap(),
+                    row.try_get("", "name").unwrap(),
+                    row.try_get("", "is_current").unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            semesters,
+            vec![
+                (2022, 1, None, false),
+                (2023, 1, None, false),
+            ]
+        );
+    }
+
+    /// 检查回滚到初始表结构后的数据
+    async fn assert_rolled_back(db: &DatabaseConnection) {
+        let manager = SchemaManager::new(db);
+        assert!(!manager.has_column("course", "coursegroup_id").await.unwrap());
+        assert!(!manager.has_column("review", "course_id").await.unwrap());
+        assert!(!manager.has_table("achievement").await.unwrap());
+        assert!(!manager.has_table("user_achievement").await.unwrap());
+        assert!(!manager.has_table("semester").await.unwrap());
+        assert_eq!(
+            query_pairs(db, "SELECT course_id, coursegroup_id FROM coursegroup_course ORDER BY course_id").await,
+            vec![(1, Some(1)), (2, Some(1))]
+        );
+        assert_eq!(
+            query_pairs(db, "SELECT review_id, course_id FROM course_review ORDER BY review_id").await,
+            vec![(1, Some(2))]
+        );
+        assert_eq!(query_pairs(db, "SELECT CAST(COUNT(*) AS INTEGER), NULL FROM userextra").await, vec![(3, None)]);
+    }
+
+    /// 各测试共用同一数据库时不能并行，因此依次执行
+    #[tokio::test]
+    async fn test_all() {
+        test_up_down_up().await;
+        test_invalid_userextra().await;
+        test_duplicate_achievements().await;
+    }
+
+    async fn test_up_down_up() {
+        let db = connect().await;
+        db.execute_unprepared(SEED).await.unwrap();
+
+        Migrator::up(&db, None).await.unwrap();
+        assert_migrated(&db).await;
+
+        let applied = Migrator::get_applied_migrations(&db).await.unwrap().len() as u32;
+        Migrator::down(&db, Some(applied - INITIAL_MIGRATIONS)).await.unwrap();
+        assert_rolled_back(&db).await;
+
+        Migrator::up(&db, None).await.unwrap();
+        assert_migrated(&db).await;
+
+        // 完全回滚后不留下任何表，并能重新迁移
+        Migrator::down(&db, None).await.unwrap();
+        let manager = SchemaManager::new(&db);
+        for table in ["course", "coursegroup", "review", "userextra", "achievement", "erasure_audit", "semester"] {
+            assert!(!manager.has_table(table).await.unwrap());
+        }
+        Migrator::up(&db, None).await.unwrap();
+        assert!(manager.has_table("erasure_audit").await.unwrap());
+    }
+
+    async fn test_invalid_userextra() {
+        let db = connect().await;
+        db.execute_unprepared(
+            r#"INSERT INTO userextra (user_id, extra) VALUES (1, '{"achievements": [{"name": "First Review", "obtain_date": "yesterday"}]}')"#,
+        )
+            .await
+            .unwrap();
+        let err = Migrator::up(&db, None).await.unwrap_err();
+        assert!(err.to_string().contains("invalid `obtain_date` yesterday"));
+    }
+
+    async fn test_duplicate_achievements() {
+        let db = connect().await;
+        // 执行到添加成就描述的迁移，之后管理员可以创建重复的成就
+        Migrator::up(&db, Some(3)).await.unwrap();
+        // 名称前 191 个字符相同的两个成就，MySQL 只按前缀建立索引时会冲突
+        let prefix = "A".repeat(200);
+        db.execute_unprepared(&format!(
+            r#"INSERT INTO achievement (id, name, domain) VALUES
+                (1, 'First Review', 'curriculum'), (2, 'First Review', NULL), (3, 'First Review', 'curriculum'),
+                (4, 'First review', 'curriculum'), (5, 'First Review', NULL), (6, '{0}a', NULL), (7, '{0}b', NULL);
+            INSERT INTO user_achievement (user_id, achievement_id, obtain_date) VALUES
+                (1, 1, '2023-02-01 00:00:00'), (1, 3, '2023-01-01 00:00:00'), (2, 2, '2023-03-01 00:00:00'),
+                (2, 5, '2023-01-01 00:00:00'), (3, 4, '2023-04-01 00:00:00');"#,
+            prefix
+        ))
+            .await
+            .unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        // 名称和领域都相同的成就被合并，领域不同的成就保留。只有大小写不同的名称是否重复
+        // 取决于数据库的排序规则：MySQL 默认不区分大小写，SQLite 和 Postgres 区分
+        let case_insensitive = query_pairs(&db, "SELECT CASE WHEN 'First review' = 'First Review' THEN 1 ELSE 0 END, NULL").await[0].0 == 1;
+        let first_review_id = if case_insensitive { 1 } else { 4 };
+        let mut expected = vec![(1, None), (2, None), (6, None), (7, None)];
+        if !case_insensitive {
+            expected.insert(2, (4, None));
+        }
+        assert_eq!(query_pairs(&db, "SELECT id, NULL FROM achievement ORDER BY id").await, expected);
+        // 重复获得的成就只保留最早的一条
+        assert_eq!(
+            query_pairs(&db, "SELECT user_id, achievement_id FROM user_achievement ORDER BY user_id").await,
+            vec![(1, Some(1)), (2, Some(2)), (3, Some(first_review_id))]
+        );
+        let rows = db
+            .query_all(Statement::from_string(
+                db.get_database_backend(),
+                "SELECT obtain_date FROM user_achievement WHERE user_id IN (1, 2) ORDER BY user_id".to_string(),
+            ))
+            .await
+            .unwrap();
+        for row in rows {
+            let obtain_date: chrono::NaiveDateTime = row.try_get("", "obtain_date").unwrap();
+            assert_eq!(obtain_date.to_string(), "2023-01-01 00:00:00");
+        }
+
+        // 唯一索引阻止重复插入，同名但领域不同的成就可以插入
+        for sql in [
+            "INSERT INTO achievement (id, name, domain) VALUES (100, 'First Review', 'curriculum')",
+            "INSERT INTO user_achievement (user_id, achievement_id, obtain_date) VALUES (1, 1, '2023-04-01 00:00:00')",
+        ] {
+            assert!(db.execute_unprepared(sql).await.is_err());
+        }
+        db.execute_unprepared("INSERT INTO achievement (id, name, domain) VALUES (101, 'First Review', 'forum')")
+            .await
+            .unwrap();
+    }
+}
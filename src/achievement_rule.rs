@@ -0,0 +1,211 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::read_to_string;
+use chrono::Local;
+use entity::prelude::*;
+use entity::review::{Userextra, ERASED_REVIEWER_ID};
+use entity::{achievement, course, review, user_achievement};
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::api::error_handler::is_unique_violation;
+
+/// 自动授予成就的条件
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    /// 发表的评论数不少于 `at_least` 条。`at_least = 1` 即为“首条评论”
+    ReviewCount { at_least: usize },
+    /// 所有评论获得的净赞数（赞同数减去反对数）之和不少于 `at_least`
+    TotalRemark { at_least: i64 },
+    /// 评论过的课程来自不少于 `at_least` 个院系
+    DepartmentCount { at_least: usize },
+}
+
+impl Condition {
+    pub fn is_met(&self, stats: &UserStats) -> bool {
+        match self {
+            Condition::ReviewCount { at_least } => stats.review_count >= *at_least,
+            Condition::TotalRemark { at_least } => stats.total_remark >= *at_least,
+            Condition::DepartmentCount { at_least } => stats.departments.len() >= *at_least,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AchievementRule {
+    /// 授予的成就名称。成就不存在时将自动创建
+    pub achievement: String,
+    pub domain: Option<String>,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub condition: Condition,
+}
+
+/// 成就规则配置文件，格式如下：
+///
+/// ```toml
+/// [[rules]]
+/// achievement = "初来乍到"
+/// domain = "curriculum"
+/// condition = { type = "review_count", at_least = 1 }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AchievementRules {
+    #[serde(default)]
+    pub rules: Vec<AchievementRule>,
+}
+
+/// 用户评论的统计信息，用于评估成就规则和展示用户主页
+#[derive(Debug, Clone, Default)]
+pub struct UserStats {
+    pub review_count: usize,
+    pub total_upvotes: i64,
+    pub total_remark: i64,
+    pub departments: HashSet<String>,
+}
+
+impl UserStats {
+    fn add(&mut self, review: &review::Model, course: Option<&course::Model>) {
+        let count = |voters: &serde_json::Value| voters.as_array().map_or(0, |v| v.len() as i64);
+        self.review_count += 1;
+        self.total_upvotes += count(&review.upvoters);
+        self.total_remark += count(&review.upvoters) - count(&review.downvoters);
+        if let Some(course) = course {
+            self.departments.insert(course.department.clone());
+        }
+    }
+
+    pub async fn load(user_id: i32, db: &DatabaseConnection) -> Result<Self, DbErr> {
+        let reviews: Vec<(review::Model, Option<course::Model>)> = Review::find()
+            .filter(review::Column::ReviewerId.eq(user_id))
+            .find_also_related(Course)
+            .all(db)
+            .await?;
+        let mut stats = UserStats::default();
+        for (review, course) in &reviews {
+            stats.add(review, course.as_ref());
+        }
+        Ok(stats)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct BackfillResult {
+    pub users_evaluated: usize,
+    pub achievements_awarded: usize,
+}
+
+impl AchievementRules {
+    /// 从 TOML 文件读取规则。未指定文件时不启用自动授予。
+    pub fn load(path: Option<&str>) -> Result<Self, String> {
+        match path {
+            Some(path) => {
+                let content = read_to_string(path)
+                    .map_err(|e| format!("Unable to read achievement rules from `{}`. Error: {}", path, e))?;
+                toml::from_str(&content)
+                    .map_err(|e| format!("Unable to parse achievement rules from `{}`. Error: {}", path, e))
+            }
+            None => Ok(AchievementRules::default()),
+        }
+    }
+
+    async fn find_achievement(rule: &AchievementRule, db: &DatabaseConnection) -> Result<Option<achievement::Model>, DbErr> {
+        Achievement::find_by_name_and_domain(&rule.achievement, rule.domain.as_deref())
+            .one(db)
+            .await
+    }
+
+    /// 成就的名称和领域共同唯一。并发创建同一成就时，插入失败的一方读取已创建的成就
+    async fn find_or_create_achievement(
+        rule: &AchievementRule,
+        db: &DatabaseConnection,
+    ) -> Result<achievement::Model, DbErr> {
+        if let Some(existing) = Self::find_achievement(rule, db).await? {
+            return Ok(existing);
+        }
+        let inserted = achievement::ActiveModel {
+            id: NotSet,
+            name: Set(rule.achievement.clone()),
+            domain: Set(rule.domain.clone()),
+            description: Set(rule.description.clone()),
+            icon: Set(rule.icon.clone()),
+        }
+            .insert(db)
+            .await;
+        match inserted {
+            Err(e) if is_unique_violation(&e) => Self::find_achievement(rule, db)
+                .await?
+                .ok_or(e),
+            result => result,
+        }
+    }
+
+    /// 根据统计信息为用户授予满足条件的成就，已拥有的成就不会重复授予。
+    /// 返回新授予的成就数量。
+    async fn award(&self, user_id: i32, stats: &UserStats, db: &DatabaseConnection) -> Result<usize, DbErr> {
+        let mut awarded = 0;
+        for rule in self.rules.iter().filter(|rule| rule.condition.is_met(stats)) {
+            let achievement = Self::find_or_create_achievement(rule, db).await?;
+            let granted = UserAchievement::find_by_id((user_id, achievement.id))
+                .one(db)
+                .await?;
+            if granted.is_some() {
+                continue;
+            }
+            let inserted = user_achievement::ActiveModel {
+                user_id: Set(user_id),
+                achievement_id: Set(achievement.id),
+                obtain_date: Set(Local::now().naive_utc()),
+            }
+                .insert(db)
+                .await;
+            match inserted {
+                Ok(_) => awarded += 1,
+                // 同时处理的另一个请求已经授予了该成就
+                Err(e) if is_unique_violation(&e) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        if awarded > 0 {
+            Userextra::invalidate(user_id).await;
+        }
+        Ok(awarded)
+    }
+
+    /// 在用户发表评论或评论被投票后调用。
+    pub async fn evaluate(&self, user_id: i32, db: &DatabaseConnection) -> Result<usize, DbErr> {
+        if self.rules.is_empty() || user_id == ERASED_REVIEWER_ID {
+            return Ok(0);
+        }
+        let stats = UserStats::load(user_id, db).await?;
+        self.award(user_id, &stats, db).await
+    }
+
+    /// 对所有历史评论重新评估规则，用于新增规则后补发成就。
+    pub async fn backfill(&self, db: &DatabaseConnection) -> Result<BackfillResult, DbErr> {
+        let reviews: Vec<(review::Model, Option<course::Model>)> = Review::find()
+            .filter(review::Column::ReviewerId.ne(ERASED_REVIEWER_ID))
+            .find_also_related(Course)
+            .all(db)
+            .await?;
+        let mut user_stats: HashMap<i32, UserStats> = HashMap::new();
+        for (review, course) in &reviews {
+            user_stats
+                .entry(review.reviewer_id)
+                .or_default()
+                .add(review, course.as_ref());
+        }
+
+        let mut result = BackfillResult {
+            users_evaluated: user_stats.len(),
+            achievements_awarded: 0,
+        };
+        for (user_id, stats) in &user_stats {
+            result.achievements_awarded += self.award(*user_id, stats, db).await?;
+        }
+        Ok(result)
+    }
+}
@@ -0,0 +1,331 @@
+use crate::achievement_rule::AchievementRules;
+use crate::api::auth::require_authentication;
+use crate::api::error_handler::{is_unique_violation, path_id, ApiError};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use chrono::{Local, NaiveDateTime};
+use entity::achievement::{GetAchievementInfo, NewAchievement, MAX_NAME_LENGTH};
+use entity::prelude::*;
+use entity::review::Userextra;
+use entity::user_achievement::GetAchievement;
+use entity::{achievement, user_achievement};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, ModelTrait, QueryFilter,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+async fn find_achievement(
+    achievement_id: i32,
+    db: &DatabaseConnection,
+) -> Result<achievement::Model, ApiError> {
+    Achievement::find_by_id(achievement_id)
+        .one(db)
+        .await
+        .map_err(ApiError::db("Unable to fetch the achievement."))?
+        .ok_or(ApiError::AchievementNotFound { id: achievement_id })
+}
+
+fn duplicate_achievement(new_achievement: &NewAchievement) -> ApiError {
+    ApiError::DuplicateAchievement {
+        name: new_achievement.name.clone(),
+        domain: new_achievement.domain.clone(),
+    }
+}
+
+/// 校验名称和领域的长度，并检查是否已有名称和领域都相同的成就（修改时不包括成就自身）。
+/// 唯一索引中 NULL 互不相等，不能阻止没有领域的同名成就，因此在写入前检查
+async fn check_new_achievement(
+    new_achievement: &NewAchievement,
+    achievement_id: Option<i32>,
+    db: &DatabaseConnection,
+) -> Result<(), ApiError> {
+    for (field, value) in [("name", Some(&new_achievement.name)), ("domain", new_achievement.domain.as_ref())] {
+        if value.is_some_and(|value| value.chars().count() > MAX_NAME_LENGTH) {
+            return Err(ApiError::InvalidRequest {
+                reason: format!("`{}` cannot be longer than {} characters.", field, MAX_NAME_LENGTH),
+            });
+        }
+    }
+    let existing = Achievement::find_by_name_and_domain(&new_achievement.name, new_achievement.domain.as_deref())
+        .one(db)
+        .await
+        .map_err(ApiError::db("Unable to fetch the achievement."))?;
+    match existing {
+        Some(existing) if Some(existing.id) != achievement_id => Err(duplicate_achievement(new_achievement)),
+        _ => Ok(()),
+    }
+}
+
+/// 插入或修改成就时，名称和领域与已有成就重复返回 409
+fn achievement_error(new_achievement: &NewAchievement, context: &'static str) -> impl FnOnce(DbErr) -> ApiError {
+    let duplicate = duplicate_achievement(new_achievement);
+    move |e| {
+        if is_unique_violation(&e) {
+            duplicate
+        } else {
+            ApiError::db(context)(e)
+        }
+    }
+}
+
+async fn user_achievements_response(
+    user_id: i32,
+    db: &DatabaseConnection,
+) -> actix_web::Result<HttpResponse> {
+    let achievements = GetAchievement::load(user_id, db)
+        .await
+        .map_err(ApiError::db("Unable to load the achievements of the user."))?;
+    Ok(HttpResponse::Ok().json(achievements))
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "All achievements.", body = [GetAchievementInfo]),
+),
+security(("auth" = []))
+)]
+#[get("/achievements")]
+pub async fn get_achievements(
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    require_authentication(&req).await?;
+    let achievements: Vec<achievement::Model> = Achievement::find()
+        .all(db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to fetch achievements."))?;
+    Ok(HttpResponse::Ok().json(
+        achievements
+            .into_iter()
+            .map(GetAchievementInfo::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+#[utoipa::path(
+request_body = NewAchievement,
+responses(
+(status = 200, description = "Achievement created successfully.", body = GetAchievementInfo),
+(status = 403, description = "Only admin can manage achievements.", body = ErrorMessage),
+(status = 409, description = "An achievement with the same name and domain already exists.", body = ErrorMessage),
+),
+security(("auth" = []))
+)]
+#[post("/achievements")]
+pub async fn add_achievement(
+    new_achievement: web::Json<NewAchievement>,
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    user_info.require_admin()?;
+    check_new_achievement(&new_achievement, None, db.get_ref()).await?;
+    let on_error = achievement_error(&new_achievement, "Unable to create new achievement.");
+    let achievement_added: achievement::Model = new_achievement
+        .into_inner()
+        .into_active_model()
+        .insert(db.get_ref())
+        .await
+        .map_err(on_error)?;
+    Ok(HttpResponse::Ok().json(GetAchievementInfo::from(achievement_added)))
+}
+
+#[utoipa::path(
+params(("achievement_id" = i32, Path, description = "Id of the achievement.")),
+request_body = NewAchievement,
+responses(
+(status = 200, description = "Achievement modified successfully.", body = GetAchievementInfo),
+(status = 403, description = "Only admin can manage achievements.", body = ErrorMessage),
+(status = 404, description = "Achievement with given id not found.", body = ErrorMessage),
+(status = 409, description = "An achievement with the same name and domain already exists.", body = ErrorMessage),
+),
+security(("auth" = []))
+)]
+#[put("/achievements/{achievement_id}")]
+pub async fn modify_achievement(
+    new_achievement: web::Json<NewAchievement>,
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    user_info.require_admin()?;
+    let achievement_id = path_id(&req, "achievement_id")?;
+    let achievement = find_achievement(achievement_id, db.get_ref()).await?;
+    check_new_achievement(&new_achievement, Some(achievement_id), db.get_ref()).await?;
+    let on_error = achievement_error(&new_achievement, "Unable to update the achievement.");
+
+    let mut updated_achievement: achievement::ActiveModel = achievement.into();
+    updated_achievement.update_with(new_achievement.into_inner());
+    let updated_achievement = updated_achievement
+        .update(db.get_ref())
+        .await
+        .map_err(on_error)?;
+    // 所有持有该成就的用户缓存都已过时
+    Userextra::invalidate_all();
+    Ok(HttpResponse::Ok().json(GetAchievementInfo::from(updated_achievement)))
+}
+
+#[utoipa::path(
+params(("achievement_id" = i32, Path, description = "Id of the achievement.")),
+responses(
+(status = 200, description = "Achievement deleted successfully. It is also revoked from all users.", body = GetAchievementInfo),
+(status = 403, description = "Only admin can manage achievements.", body = ErrorMessage),
+(status = 404, description = "Achievement with given id not found.", body = ErrorMessage),
+),
+security(("auth" = []))
+)]
+#[delete("/achievements/{achievement_id}")]
+pub async fn delete_achievement(
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    user_info.require_admin()?;
+    let achievement_id = path_id(&req, "achievement_id")?;
+    let achievement = find_achievement(achievement_id, db.get_ref()).await?;
+
+    let delete_error = "Unable to delete the achievement.";
+    let transaction = db.begin().await.map_err(ApiError::db(delete_error))?;
+    UserAchievement::delete_many()
+        .filter(user_achievement::Column::AchievementId.eq(achievement_id))
+        .exec(&transaction)
+        .await
+        .map_err(ApiError::db(delete_error))?;
+    achievement
+        .clone()
+        .delete(&transaction)
+        .await
+        .map_err(ApiError::db(delete_error))?;
+    transaction.commit().await.map_err(ApiError::db(delete_error))?;
+
+    Userextra::invalidate_all();
+    Ok(HttpResponse::Ok().json(GetAchievementInfo::from(achievement)))
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct GrantAchievement {
+    pub achievement_id: i32,
+    /// 获得成就的时间，默认为当前时间
+    pub obtain_date: Option<NaiveDateTime>,
+}
+
+#[utoipa::path(
+params(("user_id" = i32, Path, description = "Id of the user.")),
+request_body = GrantAchievement,
+responses(
+(status = 200, description = "Achievement granted successfully. Returns all achievements of the user.", body = [GetAchievement]),
+(status = 403, description = "Only admin can manage achievements.", body = ErrorMessage),
+(status = 404, description = "Achievement with given id not found.", body = ErrorMessage),
+(status = 409, description = "The user already has this achievement.", body = ErrorMessage,
+example = json ! (ApiError::DuplicateUserAchievement { user_id: 1, achievement_id: 1 }.to_message()))
+),
+security(("auth" = []))
+)]
+#[post("/users/{user_id}/achievements")]
+pub async fn grant_achievement(
+    grant: web::Json<GrantAchievement>,
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    user_info.require_admin()?;
+    let user_id = path_id(&req, "user_id")?;
+    let grant = grant.into_inner();
+    find_achievement(grant.achievement_id, db.get_ref()).await?;
+
+    let granted = UserAchievement::find_by_id((user_id, grant.achievement_id))
+        .one(db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to fetch the achievements of the user."))?;
+    if granted.is_some() {
+        return Err(ApiError::DuplicateUserAchievement {
+            user_id,
+            achievement_id: grant.achievement_id,
+        }
+        .into());
+    }
+
+    user_achievement::ActiveModel {
+        user_id: Set(user_id),
+        achievement_id: Set(grant.achievement_id),
+        obtain_date: Set(grant
+            .obtain_date
+            .unwrap_or_else(|| Local::now().naive_utc())),
+    }
+        .insert(db.get_ref())
+        .await
+        .map_err(|e| {
+            // 并发授予同一成就时，由唯一索引保证只授予一次
+            if is_unique_violation(&e) {
+                ApiError::DuplicateUserAchievement {
+                    user_id,
+                    achievement_id: grant.achievement_id,
+                }
+            } else {
+                ApiError::db("Unable to grant the achievement.")(e)
+            }
+        })?;
+
+    Userextra::invalidate(user_id).await;
+    user_achievements_response(user_id, db.get_ref()).await
+}
+
+#[utoipa::path(
+params(("user_id" = i32, Path, description = "Id of the user."), ("achievement_id" = i32, Path, description = "Id of the achievement.")),
+responses(
+(status = 200, description = "Achievement revoked successfully. Returns all achievements of the user.", body = [GetAchievement]),
+(status = 403, description = "Only admin can manage achievements.", body = ErrorMessage),
+(status = 404, description = "The user does not have this achievement.", body = ErrorMessage),
+),
+security(("auth" = []))
+)]
+#[delete("/users/{user_id}/achievements/{achievement_id}")]
+pub async fn revoke_achievement(
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    user_info.require_admin()?;
+    let user_id = path_id(&req, "user_id")?;
+    let achievement_id = path_id(&req, "achievement_id")?;
+
+    let granted: user_achievement::Model = UserAchievement::find_by_id((user_id, achievement_id))
+        .one(db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to fetch the achievements of the user."))?
+        .ok_or(ApiError::UserAchievementNotFound {
+            user_id,
+            achievement_id,
+        })?;
+    granted
+        .delete(db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to revoke the achievement."))?;
+
+    Userextra::invalidate(user_id).await;
+    user_achievements_response(user_id, db.get_ref()).await
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Achievement rules are re-evaluated over all historical reviews.", body = BackfillResult),
+(status = 403, description = "Only admin can manage achievements.", body = ErrorMessage),
+),
+security(("auth" = []))
+)]
+#[post("/achievements/backfill")]
+pub async fn backfill_achievements(
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+    rules: web::Data<AchievementRules>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    user_info.require_admin()?;
+    let result = rules
+        .backfill(db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to backfill achievements."))?;
+    Ok(HttpResponse::Ok().json(result))
+}
@@ -1,11 +1,13 @@
-use std::env;
-use actix_web::{HttpRequest};
+use std::sync::OnceLock;
+use std::time::Instant;
+use actix_web::{web, HttpMessage, HttpRequest};
 use actix_web::http::StatusCode;
 use if_chain::if_chain;
 use lazy_static::lazy_static;
-use moka::future::Cache;
-use crate::api::error_handler::{internal_server_error, unauthorized};
-use crate::constant;
+use moka::future::{Cache, CacheBuilder};
+use crate::api::error_handler::{ApiError, CauseChain};
+use crate::api::metrics;
+use crate::settings::{AuthMode, CacheLimit, Settings};
 use serde::Deserialize;
 
 
@@ -15,55 +17,121 @@ pub struct UserInfo {
     pub is_admin: bool,
 }
 
+impl UserInfo {
+    pub fn require_admin(&self) -> Result<(), ApiError> {
+        if self.is_admin {
+            Ok(())
+        } else {
+            Err(ApiError::AdminRequired)
+        }
+    }
+}
+
+// 缓存的容量和存活时间，须在第一次使用缓存之前设置
+static USER_CACHE_LIMIT: OnceLock<CacheLimit> = OnceLock::new();
+
 lazy_static! {
     static ref GLOBAL_USER_CACHE: Cache<String,UserInfo> = {
-        Cache::new(10000)
+        let limit = USER_CACHE_LIMIT.get().copied().unwrap_or(CacheLimit {
+            capacity: 10000,
+            ttl_secs: None,
+        });
+        let mut builder = CacheBuilder::new(limit.capacity);
+        if let Some(time_to_live) = limit.time_to_live() {
+            builder = builder.time_to_live(time_to_live);
+        }
+        builder.build()
     };
 }
 
-async fn request_user_info(header: &str) -> Result<UserInfo, actix_web::Error> {
+/// 设置缓存的容量和存活时间。缓存创建之后再设置无效，返回 `false`
+pub fn configure_user_cache(limit: CacheLimit) -> bool {
+    USER_CACHE_LIMIT.set(limit).is_ok()
+}
+
+async fn request_user_info(url: &str, header: &str) -> Result<UserInfo, ApiError> {
     let header = header.to_string();
     let cached_value = GLOBAL_USER_CACHE.get(&header);
+    metrics::observe_cache("user", cached_value.is_some());
     if let Some(info) = cached_value {
         return Ok(info);
     }
     let client = reqwest::Client::new();
+    let start = Instant::now();
     let result =
-        client.get(env::var(constant::ENV_USER_VERIFICATION_ADDRESS).unwrap()).header("Authorization", &header).send().await;
+        client.get(url).header("Authorization", &header).send().await;
     match result {
         Ok(response) => {
             if response.status() == StatusCode::UNAUTHORIZED {
-                return Err(unauthorized("Authorization Failed.".to_string()));
+                metrics::observe_auth_upstream(start.elapsed());
+                return Err(ApiError::AuthenticationFailed);
             }
-            if let Ok(user) = response.json::<UserInfo>().await {
+            let user = response.json::<UserInfo>().await;
+            metrics::observe_auth_upstream(start.elapsed());
+            if let Ok(user) = user {
                 GLOBAL_USER_CACHE.insert(header, user).await;
                 Ok(user)
             } else {
-                Err(internal_server_error("Internal Error: Cannot validate authorization information.".to_string()))
+                metrics::observe_auth_upstream_failure("response");
+                Err(ApiError::AuthUpstream {
+                    reason: "The response is not valid user information.".to_string(),
+                })
             }
         }
-        Err(e) =>
-            Err(internal_server_error(format!("Internal Error: Cannot validate authorization information. Error: {}", e.to_string())))
+        Err(e) => {
+            metrics::observe_auth_upstream(start.elapsed());
+            metrics::observe_auth_upstream_failure("request");
+            Err(ApiError::AuthUpstream {
+                reason: format!("Error: {}", CauseChain(&e)),
+            })
+        }
     }
 }
 
-pub async fn require_authentication(req: &HttpRequest) -> Result<UserInfo, actix_web::Error> {
-    // 单元测试环境，不验证任何身份信息
+/// 单元测试中用于以普通用户身份发起请求的头，值为用户 id
+pub const TEST_USER_HEADER: &str = "X-Test-User";
+
+/// 验证请求的身份。验证通过的用户会记录在请求中，供日志使用
+pub async fn require_authentication(req: &HttpRequest) -> Result<UserInfo, ApiError> {
+    let user_info = authenticate(req).await?;
+    req.extensions_mut().insert(user_info);
+    Ok(user_info)
+}
+
+async fn authenticate(req: &HttpRequest) -> Result<UserInfo, ApiError> {
+    // 单元测试环境，不验证任何身份信息。带有测试用户头的请求视为该 id 的普通用户
     if cfg!(test) {
+        let test_user = req
+            .headers()
+            .get(TEST_USER_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        return Ok(match test_user {
+            Some(id) => UserInfo { id, is_admin: false },
+            None => UserInfo { id: 233, is_admin: true },
+        });
+    }
+
+    let settings = req
+        .app_data::<web::Data<Settings>>()
+        .ok_or_else(|| ApiError::internal("Authentication is not configured."))?;
+    if settings.auth.mode == AuthMode::Disabled {
         return Ok(UserInfo {
-            id: 233,
+            id: settings.auth.dev_user_id,
             is_admin: true,
         });
     }
+    // 启动时已校验 remote 模式下必须设置 url
+    let url = settings.auth.url.as_deref().unwrap_or_default();
 
     let authorization = req.headers().get("Authorization");
     if_chain! {
         if let Some(header) = authorization;
         if let Ok(header_value) = header.to_str();
         then {
-            return request_user_info(header_value).await
+            request_user_info(url, header_value).await
         } else {
-            return Err(unauthorized("Authorization Information Needed.".to_string() ))
+            Err(ApiError::AuthenticationRequired)
         }
     }
 }
\ No newline at end of file
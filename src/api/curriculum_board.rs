@@ -1,96 +1,292 @@
+use crate::achievement_rule::AchievementRules;
 use crate::api::auth::require_authentication;
-use crate::api::error_handler::{
-    bad_request, conflict, internal_server_error, not_found, unauthorized, ErrorMessage,
-};
+use crate::api::error_handler::{path_id, ApiError, CauseChain};
+use crate::api::i18n::{self, current_locale};
+use crate::api::metrics;
+use crate::api::negotiation::{ContentCoding, ResponseFormat};
+use crate::api::semester::{list_semesters, SemesterSummary};
+use crate::pseudonym::Pseudonymizer;
+use crate::settings::Settings;
 use actix_web::{get, patch, post, put, web, HttpRequest, HttpResponse, Responder};
+use actix_web::http::header;
+use actix_web::web::Bytes;
 use chrono::Local;
-use entity::course::{GetSingleCourse, NewCourse};
+use entity::course::{GetMultiCourse, GetSingleCourse, NewCourse};
 use entity::coursegroup::{GetMultiCourseGroup, GetSingleCourseGroup, NewCourseGroup};
 use entity::prelude::*;
 use entity::review::{GetMyReview, GetReview, HistoryReview, NewReview};
+use entity::semester::SemesterNames;
 use entity::{course, coursegroup, review};
 use lazy_static::lazy_static;
 use rand::Rng;
 use reqwest::StatusCode;
 use sea_orm::ActiveValue::Set;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
-    FromQueryResult, IntoActiveModel, QueryFilter, Statement,
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel,
+    PaginatorTrait, QueryFilter, TransactionTrait,
 };
+use sea_orm::sea_query::Expr;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, to_string, Value};
+use serde_json::{json, Value};
 use sha3::{Digest, Sha3_256};
-use std::sync::{RwLock, RwLockReadGuard};
-use utoipa::ToSchema;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use utoipa::{IntoParams, ToSchema};
 
 #[utoipa::path(
 responses(
-(status = 200, description = "Return debug information"),
+(status = 200, description = "Welcome page with build information, localized by `Accept-Language`.", content_type = "text/plain"),
 )
 )]
 #[get("/")]
-pub async fn hello() -> impl Responder {
-    HttpResponse::Ok().body(format!("Welcome to curriculum_board backend.\nBrowse API documents on GitHub at https://github.com/OpenTreeHole/curriculum_board_backend_next please.\n\n\
-    Current version: {}\n\
-    Build time: {}\n\
-    Rust compiler version: {}", env!("VERGEN_GIT_SHA"), env!("VERGEN_BUILD_TIMESTAMP"), env!("VERGEN_RUSTC_SEMVER")))
+pub async fn hello(settings: Option<web::Data<Settings>>) -> impl Responder {
+    let locale = current_locale();
+    // 未提供配置时与默认配置相同，开启 Swagger UI
+    let docs_enabled = settings.is_none_or(|settings| settings.docs.enabled);
+    let params = json!({
+        "docs": if docs_enabled { locale.catalog().docs.as_str() } else { "" },
+        "version": env!("VERGEN_GIT_SHA"),
+        "build_time": env!("VERGEN_BUILD_TIMESTAMP"),
+        "rustc_version": env!("VERGEN_RUSTC_SEMVER"),
+    });
+    let params = params.as_object().unwrap();
+    HttpResponse::Ok()
+        .insert_header((header::CONTENT_LANGUAGE, locale.tag()))
+        .body(i18n::render(&locale.catalog().hello, params))
+}
+/// 课程组列表的缓存。列表很大，预先按每种格式序列化并压缩，请求时直接返回
+struct CourseGroupCache {
+    /// JSON 格式未压缩内容的 SHA3-256
+    hash: String,
+    codings: &'static [ContentCoding],
+    variants: HashMap<(ResponseFormat, ContentCoding), Bytes>,
 }
+
+impl CourseGroupCache {
+    fn build(group_list: &[GetMultiCourseGroup], codings: &'static [ContentCoding]) -> Result<Self, String> {
+        let mut hash = String::new();
+        let mut variants = HashMap::new();
+        for format in ResponseFormat::ALL {
+            let payload = format.encode(group_list)?;
+            if format == ResponseFormat::Json {
+                hash = base16ct::lower::encode_string(&Sha3_256::digest(&payload));
+            }
+            for &coding in codings {
+                let compressed = coding.compress(&payload).map_err(|e| e.to_string())?;
+                variants.insert((format, coding), Bytes::from(compressed));
+            }
+        }
+        Ok(CourseGroupCache { hash, codings, variants })
+    }
+
+    /// 所有格式的缓存占用的字节数
+    fn size(&self) -> usize {
+        self.variants.values().map(Bytes::len).sum()
+    }
+
+    fn respond(&self, req: &HttpRequest) -> Result<HttpResponse, ApiError> {
+        let format = ResponseFormat::negotiate(req);
+        let coding = ContentCoding::negotiate(req, self.codings);
+        let body = self.variants.get(&(format, coding)).ok_or_else(missing_cache)?;
+        Ok(HttpResponse::Ok()
+            .content_type(format.content_type())
+            .insert_header((header::CONTENT_ENCODING, coding.header_value()))
+            .insert_header((header::VARY, "Accept, Accept-Encoding"))
+            .body(body.clone()))
+    }
+}
+
+/// 课程组列表的筛选条件，每种条件有各自的缓存和哈希
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum CourseListFilter {
+    All,
+    /// 指定学年和（或）学期
+    Semester { year: Option<i32>, semester: Option<i32> },
+    /// 有课程的最近若干个学期
+    Latest(u32),
+}
+
+impl CourseListFilter {
+    /// 预先压缩的编码。Brotli 压缩较慢，只用于请求最多的全部课程组列表
+    fn codings(&self) -> &'static [ContentCoding] {
+        match self {
+            CourseListFilter::All => &ContentCoding::ALL,
+            _ => &[ContentCoding::Identity, ContentCoding::Gzip],
+        }
+    }
+}
+
+/// `latest` 的上限，也限制了此类缓存的数量
+const MAX_LATEST_SEMESTERS: u32 = 16;
+
+#[derive(Deserialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CourseListOptions {
+    /// Only list courses in this year. Course groups without such courses are omitted.
+    pub year: Option<i32>,
+    /// Only list courses in this semester. Applies the same way as `year`.
+    pub semester: Option<i32>,
+    /// Only list courses in the latest `latest` semesters that have courses, at most 16. Cannot be used with `year` or `semester`.
+    pub latest: Option<u32>,
+}
+
+impl CourseListOptions {
+    fn filter(&self) -> Result<CourseListFilter, ApiError> {
+        match (self.year, self.semester, self.latest) {
+            (None, None, None) => Ok(CourseListFilter::All),
+            (year, semester, None) => Ok(CourseListFilter::Semester { year, semester }),
+            (None, None, Some(latest)) if (1..=MAX_LATEST_SEMESTERS).contains(&latest) => {
+                Ok(CourseListFilter::Latest(latest))
+            }
+            (None, None, Some(_)) => Err(ApiError::InvalidRequest {
+                reason: format!("`latest` must be between 1 and {}.", MAX_LATEST_SEMESTERS),
+            }),
+            _ => Err(ApiError::InvalidRequest {
+                reason: "`latest` cannot be used with `year` or `semester`.".to_string(),
+            }),
+        }
+    }
+}
+
 lazy_static! {
-    static ref COURSE_GROUP_CACHE: RwLock<Option<String>> = RwLock::new(None);
-    static ref COURSE_GROUP_HASH_CACHE: RwLock<Option<String>> = RwLock::new(None);
+    static ref COURSE_GROUP_CACHE: RwLock<HashMap<CourseListFilter, Arc<CourseGroupCache>>> = RwLock::new(HashMap::new());
 }
-async fn build_course_group_cache(
+
+/// 每次清空缓存时递增。构建期间缓存被清空的，构建结果可能已经过时，不再写入缓存
+static COURSE_GROUP_CACHE_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// 全部课程组的缓存是否构建成功过。之后缓存即使被清空，也会在下次请求时重建
+static COURSE_GROUP_CACHE_BUILT: AtomicBool = AtomicBool::new(false);
+
+/// 启动时预热缓存失败后的重试间隔
+const CACHE_WARM_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 查询符合条件的课程组，课程列表中只保留符合条件的课程
+async fn find_course_groups(
     db: &DatabaseConnection,
-) -> Result<RwLockReadGuard<Option<String>>, DbErr> {
+    filter: CourseListFilter,
+) -> Result<Vec<GetMultiCourseGroup>, DbErr> {
+    let mut condition = Condition::all();
+    match filter {
+        CourseListFilter::All => {}
+        CourseListFilter::Semester { year, semester } => {
+            if let Some(year) = year {
+                condition = condition.add(course::Column::Year.eq(year));
+            }
+            if let Some(semester) = semester {
+                condition = condition.add(course::Column::Semester.eq(semester));
+            }
+        }
+        CourseListFilter::Latest(count) => {
+            let semesters: Vec<SemesterSummary> = list_semesters(db)
+                .await?
+                .into_iter()
+                .filter(|summary| summary.course_count > 0)
+                .take(count as usize)
+                .collect();
+            if semesters.is_empty() {
+                return Ok(vec![]);
+            }
+            let mut semester_condition = Condition::any();
+            for summary in semesters {
+                semester_condition = semester_condition.add(
+                    Condition::all()
+                        .add(course::Column::Year.eq(summary.year))
+                        .add(course::Column::Semester.eq(summary.semester)),
+                );
+            }
+            condition = condition.add(semester_condition);
+        }
+    }
     let result: Vec<(coursegroup::Model, Vec<course::Model>)> = Coursegroup::find()
         .find_with_related(Course)
+        .filter(condition)
         .all(db)
         .await?;
-    let mut group_list: Vec<GetMultiCourseGroup> = vec![];
-    for x in result {
-        group_list.push(GetMultiCourseGroup::new(x.0, x.1));
-    }
-
-    let mut cache_writer = COURSE_GROUP_CACHE.write().unwrap();
-    *cache_writer = Some(to_string(&group_list).map_err(|e| DbErr::Custom(e.to_string()))?);
-    drop(cache_writer);
+    let names = SemesterNames::load(db).await?;
+    Ok(result
+        .into_iter()
+        .map(|(group, courses)| GetMultiCourseGroup::new(group, courses, &names))
+        .collect())
+}
 
-    let mut cache_writer = COURSE_GROUP_HASH_CACHE.write().unwrap();
-    let cache_reader = COURSE_GROUP_CACHE.read().unwrap();
-    let hash = Sha3_256::digest(cache_reader.as_ref().unwrap().as_bytes());
-    let hex_hash = base16ct::lower::encode_string(&hash);
-    *cache_writer = Some(hex_hash);
-    drop(cache_writer);
-    drop(cache_reader);
+async fn build_course_group_cache(
+    db: &DatabaseConnection,
+    filter: CourseListFilter,
+) -> Result<Arc<CourseGroupCache>, DbErr> {
+    let start = Instant::now();
+    // 须在查询之前读取，查询之后才清空缓存的，查询结果可能不包括最新的修改
+    let generation = COURSE_GROUP_CACHE_GENERATION.load(Ordering::Acquire);
+    let group_list = find_course_groups(db, filter).await?;
+    // 没有课程的筛选条件不缓存，避免任意的学年占用缓存
+    let cacheable = filter == CourseListFilter::All || !group_list.is_empty();
+
+    // 序列化和压缩耗时较长，放到线程池中执行
+    let cache = web::block(move || CourseGroupCache::build(&group_list, filter.codings()))
+        .await
+        .map_err(|e| DbErr::Custom(e.to_string()))?
+        .map_err(DbErr::Custom)?;
+    let cache = Arc::new(cache);
+    if cacheable {
+        let mut caches = COURSE_GROUP_CACHE.write().unwrap();
+        // 构建期间缓存被清空了，本次结果只用于当前请求
+        if COURSE_GROUP_CACHE_GENERATION.load(Ordering::Acquire) != generation {
+            return Ok(cache);
+        }
+        caches.insert(filter, cache.clone());
+        if filter == CourseListFilter::All {
+            COURSE_GROUP_CACHE_BUILT.store(true, Ordering::Relaxed);
+        }
+        metrics::observe_course_cache_rebuild(start.elapsed(), caches.values().map(|cache| cache.size()).sum());
+    }
+    Ok(cache)
+}
 
-    Ok(COURSE_GROUP_CACHE.read().unwrap())
+pub fn invalidate_course_group_cache() {
+    let mut caches = COURSE_GROUP_CACHE.write().unwrap();
+    COURSE_GROUP_CACHE_GENERATION.fetch_add(1, Ordering::AcqRel);
+    caches.clear();
 }
 
 async fn get_course_group_cache(
     db: &DatabaseConnection,
-) -> Result<RwLockReadGuard<Option<String>>, DbErr> {
-    let cache = COURSE_GROUP_CACHE.read().unwrap();
-    if cache.is_none() {
-        drop(cache);
-        Ok(build_course_group_cache(db).await?)
-    } else {
-        Ok(cache)
+    filter: CourseListFilter,
+) -> Result<Arc<CourseGroupCache>, DbErr> {
+    let cache = COURSE_GROUP_CACHE.read().unwrap().get(&filter).cloned();
+    metrics::observe_cache("course_group", cache.is_some());
+    match cache {
+        Some(cache) => Ok(cache),
+        None => build_course_group_cache(db, filter).await,
     }
 }
 
-async fn get_course_group_hash_cache(
-    db: &DatabaseConnection,
-) -> Result<RwLockReadGuard<Option<String>>, DbErr> {
-    let cache = COURSE_GROUP_HASH_CACHE.read().unwrap();
-    if cache.is_none() {
-        drop(cache);
-        drop(build_course_group_cache(db).await?);
-        Ok(COURSE_GROUP_HASH_CACHE.read().unwrap())
-    } else {
-        Ok(cache)
+pub fn is_course_group_cache_warm() -> bool {
+    COURSE_GROUP_CACHE.read().unwrap().contains_key(&CourseListFilter::All)
+}
+
+pub fn is_course_group_cache_built() -> bool {
+    COURSE_GROUP_CACHE_BUILT.load(Ordering::Relaxed)
+}
+
+/// 在后台预热全部课程组的缓存。失败时（如迁移尚未执行）定期重试，直到成功为止
+pub async fn warm_course_group_cache(db: DatabaseConnection) {
+    loop {
+        match build_course_group_cache(&db, CourseListFilter::All).await {
+            Ok(_) if is_course_group_cache_built() => return,
+            // 构建期间缓存被清空，结果没有写入缓存，立即重新构建
+            Ok(_) => continue,
+            Err(e) => tracing::warn!(error = %e, "unable to warm the course group cache"),
+        }
+        actix_web::rt::time::sleep(CACHE_WARM_RETRY_INTERVAL).await;
     }
 }
 
+fn missing_cache() -> ApiError {
+    ApiError::internal("Missing cache. The server did build the cache but the cache seems to be none.")
+}
+
 #[utoipa::path(
 responses(
 (status = 418, description = "Refresh cache successfully"),
@@ -98,10 +294,7 @@ responses(
 )]
 #[get("/courses/refresh")]
 pub async fn refresh_course_groups_cache(_unused: HttpRequest) -> impl Responder {
-    let mut cache_writer = COURSE_GROUP_CACHE.write().unwrap();
-    *cache_writer = None;
-    let mut cache_writer = COURSE_GROUP_HASH_CACHE.write().unwrap();
-    *cache_writer = None;
+    invalidate_course_group_cache();
     HttpResponse::build(StatusCode::IM_A_TEAPOT).body("I'm a brand new empty teapot now!")
 }
 
@@ -110,104 +303,121 @@ pub struct HashMessage {
     pub hash: String,
 }
 
+#[derive(Deserialize, Debug, Clone, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReviewListOptions {
+    /// Whether to include the reviewers' achievements (`extra`) in every review.
+    /// Defaults to true; pass `false` to get smaller responses.
+    #[serde(default = "default_extra")]
+    pub extra: bool,
+}
+
+// 与之前的版本保持一致，默认返回成就信息
+fn default_extra() -> bool {
+    true
+}
+
+impl Default for ReviewListOptions {
+    fn default() -> Self {
+        ReviewListOptions { extra: default_extra() }
+    }
+}
+
 #[utoipa::path(
+params(CourseListOptions),
 responses(
-(status = 200, description = "Hash of course group cache", body = HashMessage),
+(status = 200, description = "Hash of course group cache with the same filter as `GET /courses`", body = HashMessage),
 )
 )]
 #[get("/courses/hash")]
 pub async fn get_course_groups_hash(
-    _unused: HttpRequest,
+    options: web::Query<CourseListOptions>,
     db: web::Data<DatabaseConnection>,
 ) -> actix_web::Result<HttpResponse> {
-    let groups = get_course_group_hash_cache(db.get_ref())
+    let cache = get_course_group_cache(db.get_ref(), options.filter()?)
         .await
-        .map_err(|e| internal_server_error(e.to_string()))?;
-    let hash_str = groups.clone().ok_or(internal_server_error(
-        "Missing cache. The server did build the cache but the cache seems to be none.".to_string(),
-    ))?;
-    Ok(HttpResponse::Ok().json(HashMessage { hash: hash_str }))
+        .map_err(ApiError::db("Unable to build the course group cache."))?;
+    Ok(HttpResponse::Ok().json(HashMessage {
+        hash: cache.hash.clone(),
+    }))
 }
 
 #[utoipa::path(
+params(CourseListOptions),
 responses(
-(status = 200, description = "Course group. Reviews are not included.", body = [GetMultiCourseGroup]),
+(status = 200, description = "Course group. Reviews are not included. Also available as MessagePack or CBOR by `Accept`, \
+and pre-compressed by `Accept-Encoding`.", body = [GetMultiCourseGroup],
+content_type = ["application/json", "application/msgpack", "application/cbor"]),
 )
 )]
 #[get("/courses")]
 pub async fn get_course_groups(
-    _unused: HttpRequest,
+    options: web::Query<CourseListOptions>,
+    req: HttpRequest,
     db: web::Data<DatabaseConnection>,
 ) -> actix_web::Result<HttpResponse> {
-    let groups = get_course_group_cache(db.get_ref())
+    let cache = get_course_group_cache(db.get_ref(), options.filter()?)
         .await
-        .map_err(|e| internal_server_error(e.to_string()))?;
-    Ok(HttpResponse::Ok().content_type("application/json").body(
-        groups.clone().ok_or(internal_server_error(
-            "Missing cache. The server did build the cache but the cache seems to be none."
-                .to_string(),
-        ))?,
-    ))
+        .map_err(ApiError::db("Unable to build the course group cache."))?;
+    Ok(cache.respond(&req)?)
 }
 
 #[utoipa::path(
+params(("group_id" = i32, Path, description = "Id of the course group."), ReviewListOptions),
 responses(
-(status = 200, description = "Single course group. Reviews are also preloaded.", body = GetSingleCourseGroup),
+(status = 200, description = "Single course group. Reviews are also preloaded. Also available as MessagePack or CBOR by `Accept`.",
+body = GetSingleCourseGroup, content_type = ["application/json", "application/msgpack", "application/cbor"]),
 (status = 404, description = "Course group with given id not found.", body = ErrorMessage,
-example = json ! (ErrorMessage { message: "Course group with id 1 not found.".to_string() }))
+example = json ! (ApiError::CourseGroupNotFound { id: 1 }.to_message()))
 ),
 security(("auth" = []))
 )]
 #[get("/group/{group_id}")]
 pub async fn get_course_group(
+    options: web::Query<ReviewListOptions>,
     req: HttpRequest,
     db: web::Data<DatabaseConnection>,
+    pseudonymizer: web::Data<Pseudonymizer>,
 ) -> actix_web::Result<HttpResponse> {
     let user_info = require_authentication(&req).await?;
-    let group_id = req
-        .match_info()
-        .query("group_id")
-        .parse::<i32>()
-        .map_err(|_| bad_request(String::from("Invalid id syntax")))?;
+    let group_id = path_id(&req, "group_id")?;
     let group: Vec<(coursegroup::Model, Vec<course::Model>)> = Coursegroup::find_by_id(group_id)
         .find_with_related(Course)
         .all(db.get_ref())
         .await
-        .map_err(|e| internal_server_error(e.to_string()))?;
+        .map_err(ApiError::db("Unable to fetch the course group."))?;
     if group.is_empty() {
-        return Err(not_found(format!(
-            "Course group with id {} is not found.",
-            group_id
-        )));
+        return Err(ApiError::CourseGroupNotFound { id: group_id }.into());
     }
+    let names = SemesterNames::load(db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to fetch the semesters."))?;
     // 载入课程的评论列表
     let group_and_courses = &group[0];
     let mut course_list: Vec<GetSingleCourse> = vec![];
     for x in &group_and_courses.1 {
-        match GetSingleCourse::load(x.clone(), db.get_ref(), user_info.id).await {
-            Ok(loaded_course) => {
+        match GetSingleCourse::load(x.clone(), db.get_ref(), user_info.id, options.extra).await {
+            Ok(mut loaded_course) => {
+                loaded_course.set_semester_name(&names);
+                pseudonymizer.apply_to_course(&mut loaded_course, &user_info);
                 course_list.push(loaded_course);
             }
             Err(e) => {
-                return Err(internal_server_error(format!(
-                    "Unable to load course group with id {}. Error: {}",
-                    group_id,
-                    e.to_string()
-                )));
+                return Err(ApiError::db(format!("Unable to load course group with id {}.", group_id))(e).into());
             }
         }
     }
 
-    Ok(HttpResponse::Ok().json(GetSingleCourseGroup::new(
-        group_and_courses.0.clone(),
-        course_list,
-    )))
+    let group = GetSingleCourseGroup::new(group_and_courses.0.clone(), course_list);
+    Ok(ResponseFormat::negotiate(&req).respond(&group)?)
 }
 
 #[utoipa::path(
 request_body = NewCourse,
 responses(
 (status = 200, description = "Course created successfully.", body = GetSingleCourse),
+(status = 403, description = "Only admin can add courses.", body = ErrorMessage,
+example = json ! (ApiError::AdminRequired.to_message())),
 ),
 security(("auth" = []))
 )]
@@ -218,14 +428,12 @@ pub async fn add_course(
     db: web::Data<DatabaseConnection>,
 ) -> actix_web::Result<HttpResponse> {
     let user_info = require_authentication(&req).await?;
-    if !user_info.is_admin {
-        return Err(unauthorized(String::from("Only admin can add new course")));
-    }
+    user_info.require_admin()?;
     let group: Option<coursegroup::Model> = Coursegroup::find()
         .filter(coursegroup::Column::Code.eq(new_course.code.clone()))
         .one(db.get_ref())
         .await
-        .map_err(|e| internal_server_error(e.to_string()))?;
+        .map_err(ApiError::db("Unable to fetch the course group."))?;
 
     let new_course = new_course.into_inner();
     let group_id = match group {
@@ -236,12 +444,7 @@ pub async fn add_course(
                 .into_active_model()
                 .insert(db.get_ref())
                 .await
-                .map_err(|e| {
-                    internal_server_error(format!(
-                        "Unable to create new course group. Error: {}",
-                        e.to_string()
-                    ))
-                })?;
+                .map_err(ApiError::db("Unable to create new course group."))?;
             new_course_group.id
         }
         Some(group) => group.id,
@@ -251,61 +454,141 @@ pub async fn add_course(
         .into_active_model(group_id)
         .insert(db.get_ref())
         .await
-        .map_err(|e| {
-            internal_server_error(format!(
-                "Unable to create new course. Error: {}",
-                e.to_string()
-            ))
-        })?;
+        .map_err(ApiError::db("Unable to create new course."))?;
+    let names = SemesterNames::load(db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to fetch the semesters."))?;
+
+    let mut new_course = GetSingleCourse::from(new_course);
+    new_course.set_semester_name(&names);
+    Ok(HttpResponse::Ok().json(new_course))
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, ToSchema)]
+pub struct MergeCourseGroups {
+    /// 被合并的课程组 id。其下的课程将移动到目标课程组，随后这些课程组被删除
+    pub source_group_ids: Vec<i32>,
+}
+
+#[utoipa::path(
+params(("group_id" = i32, Path, description = "Id of the target course group.")),
+request_body = MergeCourseGroups,
+responses(
+(status = 200, description = "Course groups merged successfully. Returns the target course group.", body = GetMultiCourseGroup),
+(status = 400, description = "The id is invalid, or the target course group is also a source.", body = ErrorMessage,
+example = json ! (ApiError::InvalidMerge { group_id: 1 }.to_message())),
+(status = 403, description = "Only admin can merge course groups.", body = ErrorMessage,
+example = json ! (ApiError::AdminRequired.to_message())),
+(status = 404, description = "Course group with given id not found.", body = ErrorMessage,
+example = json ! (ApiError::CourseGroupNotFound { id: 2 }.to_message())),
+),
+security(("auth" = []))
+)]
+#[post("/group/{group_id}/merge")]
+pub async fn merge_course_groups(
+    merge: web::Json<MergeCourseGroups>,
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    user_info.require_admin()?;
+    let group_id = path_id(&req, "group_id")?;
+    let source_group_ids = merge.into_inner().source_group_ids;
+    if source_group_ids.contains(&group_id) {
+        return Err(ApiError::InvalidMerge { group_id }.into());
+    }
 
-    Ok(HttpResponse::Ok().json(GetSingleCourse::from(new_course)))
+    let mut group_ids = source_group_ids.clone();
+    group_ids.push(group_id);
+    let groups: Vec<coursegroup::Model> = Coursegroup::find()
+        .filter(coursegroup::Column::Id.is_in(group_ids.clone()))
+        .all(db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to fetch the course groups."))?;
+    if let Some(missing) = group_ids.iter().find(|id| !groups.iter().any(|group| group.id == **id)) {
+        return Err(ApiError::CourseGroupNotFound { id: *missing }.into());
+    }
+
+    let merge_error = "Unable to merge course groups.";
+    let transaction = db.begin().await.map_err(ApiError::db(merge_error))?;
+    Course::update_many()
+        .col_expr(course::Column::CoursegroupId, Expr::value(group_id))
+        .filter(course::Column::CoursegroupId.is_in(source_group_ids.clone()))
+        .exec(&transaction)
+        .await
+        .map_err(ApiError::db(merge_error))?;
+    Coursegroup::delete_many()
+        .filter(coursegroup::Column::Id.is_in(source_group_ids))
+        .exec(&transaction)
+        .await
+        .map_err(ApiError::db(merge_error))?;
+    transaction.commit().await.map_err(ApiError::db(merge_error))?;
+    invalidate_course_group_cache();
+
+    let (group, courses) = Coursegroup::find_by_id(group_id)
+        .find_with_related(Course)
+        .all(db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to load the merged course group."))?
+        .remove(0);
+    let names = SemesterNames::load(db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to fetch the semesters."))?;
+    Ok(HttpResponse::Ok().json(GetMultiCourseGroup::new(group, courses, &names)))
 }
 
 #[utoipa::path(
+params(("course_id" = i32, Path, description = "Id of the course."), ReviewListOptions),
 responses(
-(status = 200, description = "Course. Reviews are also preloaded.", body = GetSingleCourse),
+(status = 200, description = "Course. Reviews are also preloaded. Also available as MessagePack or CBOR by `Accept`.",
+body = GetSingleCourse, content_type = ["application/json", "application/msgpack", "application/cbor"]),
+(status = 404, description = "Course with given id not found.", body = ErrorMessage,
+example = json ! (ApiError::CourseNotFound { id: 1 }.to_message())),
 ),
 security(("auth" = []))
 )]
 #[get("/courses/{course_id}")]
 pub async fn get_course(
+    options: web::Query<ReviewListOptions>,
     req: HttpRequest,
     db: web::Data<DatabaseConnection>,
+    pseudonymizer: web::Data<Pseudonymizer>,
 ) -> actix_web::Result<HttpResponse> {
     let user_info = require_authentication(&req).await?;
-    let course_id = req
-        .match_info()
-        .query("course_id")
-        .parse::<i32>()
-        .map_err(|_| bad_request(String::from("Invalid id syntax")))?;
+    let course_id = path_id(&req, "course_id")?;
 
     let course: Option<course::Model> = Course::find_by_id(course_id)
         .one(db.get_ref())
         .await
-        .map_err(|e| internal_server_error(e.to_string()))?;
+        .map_err(ApiError::db("Unable to fetch the course."))?;
     if course.is_none() {
-        return Err(not_found(format!(
-            "Course with id {} is not found.",
-            course_id
-        )));
+        return Err(ApiError::CourseNotFound { id: course_id }.into());
     }
+    let names = SemesterNames::load(db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to fetch the semesters."))?;
     // 载入课程的评论列表
-    match GetSingleCourse::load(course.unwrap().clone(), db.get_ref(), user_info.id).await {
-        Ok(loaded_course) => Ok(HttpResponse::Ok().json(loaded_course)),
-        Err(e) => Err(internal_server_error(format!(
-            "Unable to load course with id {}. Error: {}",
-            course_id,
-            e.to_string()
-        ))),
+    match GetSingleCourse::load(course.unwrap().clone(), db.get_ref(), user_info.id, options.extra).await {
+        Ok(mut loaded_course) => {
+            loaded_course.set_semester_name(&names);
+            pseudonymizer.apply_to_course(&mut loaded_course, &user_info);
+            Ok(ResponseFormat::negotiate(&req).respond(&loaded_course)?)
+        }
+        Err(e) => Err(ApiError::db(format!("Unable to load course with id {}.", course_id))(e).into()),
     }
 }
 
 #[utoipa::path(
+params(("course_id" = i32, Path, description = "Id of the course to review.")),
 request_body = NewReview,
 responses(
 (status = 200, description = "Review created successfully.", body = GetReview),
+(status = 404, description = "Course with given id not found.", body = ErrorMessage,
+example = json ! (ApiError::CourseNotFound { id: 1 }.to_message())),
 (status = 409, description = "The user has already reviewed this course.", body = ErrorMessage,
-example = json ! (ErrorMessage { message: "You cannot post more than one review.".to_string() }))
+example = json ! (ApiError::DuplicateReview { course_id: 1 }.to_message())),
+(status = 429, description = "Too many reviews are posted in a short time.", body = ErrorMessage,
+example = json ! (ApiError::RateLimited { retry_after: 30 }.to_message())),
 ),
 security(("auth" = []))
 )]
@@ -314,45 +597,33 @@ pub async fn add_review(
     new_review: web::Json<NewReview>,
     req: HttpRequest,
     db: web::Data<DatabaseConnection>,
+    pseudonymizer: web::Data<Pseudonymizer>,
+    rules: web::Data<AchievementRules>,
 ) -> actix_web::Result<HttpResponse> {
     let user_info = require_authentication(&req).await?;
     let new_review = new_review.into_inner();
-    let course_id = req
-        .match_info()
-        .query("course_id")
-        .parse::<i32>()
-        .map_err(|_| bad_request(String::from("Invalid id syntax")))?;
+    let course_id = path_id(&req, "course_id")?;
 
     // 检查对应课程是否存在
     let course: Option<course::Model> = Course::find_by_id(course_id)
         .one(db.get_ref())
         .await
-        .map_err(|e| {
-            internal_server_error(format!(
-                "Unable to fetch the course. Error: {}",
-                e.to_string()
-            ))
-        })?;
-    let course = course.ok_or(not_found(format!(
-        "Course with id {} is not found.",
-        course_id
-    )))?;
+        .map_err(ApiError::db("Unable to fetch the course."))?;
+    let course = course.ok_or(ApiError::CourseNotFound { id: course_id })?;
     // 防止同一用户创建两条评论
-    let course_with_reviews = GetSingleCourse::load(course, db.get_ref(), user_info.id).await;
+    let course_with_reviews = GetSingleCourse::load(course, db.get_ref(), user_info.id, false).await;
     match course_with_reviews {
         Ok(course_with_reviews) => {
             if course_with_reviews.review_list.iter().any(|r| r.is_me) {
-                return Err(conflict(String::from(
-                    "You cannot post more than one review.",
-                )));
+                return Err(ApiError::DuplicateReview { course_id }.into());
             }
         }
         Err(err) => {
-            return Err(internal_server_error(format!(
-                "Unable to fetch the review list of Course with id {}. Error: {}",
-                course_id,
-                err.to_string()
-            )));
+            return Err(ApiError::db(format!(
+                "Unable to fetch the review list of Course with id {}.",
+                course_id
+            ))(err)
+            .into());
         }
     }
     // 创建新评论
@@ -360,26 +631,31 @@ pub async fn add_review(
         .into_active_model(user_info.id, course_id)
         .insert(db.get_ref())
         .await
-        .map_err(|err| {
-            internal_server_error(format!(
-                "Unable to create new review. Error: {}",
-                err.to_string()
-            ))
-        })?;
+        .map_err(ApiError::db("Unable to create new review."))?;
+    metrics::observe_review_created();
+    // 检查是否达成了新的成就。授予失败不影响评论的创建
+    if let Err(e) = rules.evaluate(user_info.id, db.get_ref()).await {
+        tracing::warn!(error = %CauseChain(&e), user_id = user_info.id, "unable to evaluate achievement rules");
+    }
 
-    Ok(HttpResponse::Ok().json(
-        GetReview::load(review_added, db.get_ref(), user_info.id)
-            .await
-            .map_err(|e| {
-                internal_server_error(format!("Unable to load review. Error: {}", e.to_string()))
-            })?,
-    ))
+    let mut review_added = GetReview::load(review_added, db.get_ref(), user_info.id)
+        .await
+        .map_err(ApiError::db("Unable to load review."))?;
+    pseudonymizer.apply(&mut review_added, course_id, &user_info);
+    Ok(HttpResponse::Ok().json(review_added))
 }
 
 #[utoipa::path(
+params(("review_id" = i32, Path, description = "Id of the review.")),
 request_body = NewReview,
 responses(
 (status = 200, description = "Review modified successfully.", body = GetReview),
+(status = 403, description = "Only the reviewer can modify the review.", body = ErrorMessage,
+example = json ! (ApiError::NotReviewOwner { review_id: 1 }.to_message())),
+(status = 404, description = "Review with given id not found.", body = ErrorMessage,
+example = json ! (ApiError::ReviewNotFound { id: 1 }.to_message())),
+(status = 429, description = "Too many modifications in a short time.", body = ErrorMessage,
+example = json ! (ApiError::RateLimited { retry_after: 30 }.to_message())),
 ),
 security(("auth" = []))
 )]
@@ -388,43 +664,33 @@ pub async fn modify_review(
     new_review: web::Json<NewReview>,
     req: HttpRequest,
     db: web::Data<DatabaseConnection>,
+    pseudonymizer: web::Data<Pseudonymizer>,
 ) -> actix_web::Result<HttpResponse> {
     let user_info = require_authentication(&req).await?;
     let new_review = new_review.into_inner();
-    let review_id = req
-        .match_info()
-        .query("review_id")
-        .parse::<i32>()
-        .map_err(|_| bad_request(String::from("Invalid id syntax")))?;
+    let review_id = path_id(&req, "review_id")?;
 
     let review: Option<review::Model> = Review::find_by_id(review_id)
         .one(db.get_ref())
         .await
-        .map_err(|e| internal_server_error(e.to_string()))?;
+        .map_err(ApiError::db("Unable to fetch the review."))?;
     if review.is_none() {
-        return Err(not_found(format!(
-            "Review with id {} is not found.",
-            review_id
-        )));
+        return Err(ApiError::ReviewNotFound { id: review_id }.into());
     }
     // 检查用户对 Review 的修改权限
     let review = review.unwrap();
     if review.reviewer_id != user_info.id && !user_info.is_admin {
-        return Err(unauthorized(String::from(
-            "You have no permission to modify this review!",
-        )));
+        return Err(ApiError::NotReviewOwner { review_id }.into());
     }
 
     // 储存目前的 Review
     let snapshot = serde_json::to_value(&review.clone().into() as &HistoryReview).map_err(|e| {
-        internal_server_error(format!(
+        ApiError::internal(format!(
             "Unable to encode the review into JSON value. Original error: {}",
-            e.to_string()
+            e
         ))
     })?;
-    let array_parsing_error = internal_server_error(String::from(
-        "Unable to parse the original review's history fields.",
-    ));
+    let array_parsing_error = ApiError::internal("Unable to parse the original review's history fields.");
     let mut history = (*review.history.as_array().ok_or(array_parsing_error)?).clone();
     history.push(json!({
         "alter_by":user_info.id,
@@ -438,20 +704,15 @@ pub async fn modify_review(
     let updated_review: Result<review::Model, DbErr> = updated_review.update(db.get_ref()).await;
 
     match updated_review {
-        Ok(updated_review) => Ok(HttpResponse::Ok().json(
-            GetReview::load(updated_review, db.get_ref(), user_info.id)
+        Ok(updated_review) => {
+            let course_id = updated_review.course_id.unwrap_or(-1);
+            let mut updated_review = GetReview::load(updated_review, db.get_ref(), user_info.id)
                 .await
-                .map_err(|e| {
-                    internal_server_error(format!(
-                        "Unable to load updated review. Error: {}",
-                        e.to_string()
-                    ))
-                })?,
-        )),
-        Err(err) => Err(internal_server_error(format!(
-            "Unable to update the review. Error: {}",
-            err.to_string()
-        ))),
+                .map_err(ApiError::db("Unable to load updated review."))?;
+            pseudonymizer.apply(&mut updated_review, course_id, &user_info);
+            Ok(HttpResponse::Ok().json(updated_review))
+        }
+        Err(err) => Err(ApiError::db("Unable to update the review.")(err).into()),
     }
 }
 
@@ -461,9 +722,14 @@ pub struct NewVote {
 }
 
 #[utoipa::path(
+params(("review_id" = i32, Path, description = "Id of the review.")),
 request_body = NewVote,
 responses(
 (status = 200, description = "Review voted successfully.", body = GetReview),
+(status = 404, description = "Review with given id not found.", body = ErrorMessage,
+example = json ! (ApiError::ReviewNotFound { id: 1 }.to_message())),
+(status = 429, description = "Too many votes in a short time.", body = ErrorMessage,
+example = json ! (ApiError::RateLimited { retry_after: 30 }.to_message())),
 ),
 security(("auth" = []))
 )]
@@ -472,25 +738,20 @@ pub async fn vote_for_review(
     vote_data: web::Json<NewVote>,
     req: HttpRequest,
     db: web::Data<DatabaseConnection>,
+    pseudonymizer: web::Data<Pseudonymizer>,
+    rules: web::Data<AchievementRules>,
 ) -> actix_web::Result<HttpResponse> {
     let user_info = require_authentication(&req).await?;
-    let review_id = req
-        .match_info()
-        .query("review_id")
-        .parse::<i32>()
-        .map_err(|_| bad_request(String::from("Invalid id syntax")))?;
+    let review_id = path_id(&req, "review_id")?;
 
     let vote_data = vote_data.into_inner();
     let review: Option<review::Model> = Review::find_by_id(review_id)
         .one(db.get_ref())
         .await
-        .map_err(|e| internal_server_error(e.to_string()))?;
+        .map_err(ApiError::db("Unable to fetch the review."))?;
 
     if review.is_none() {
-        return Err(not_found(format!(
-            "Review with id {} is not found.",
-            review_id
-        )));
+        return Err(ApiError::ReviewNotFound { id: review_id }.into());
     }
     let review = review.unwrap();
 
@@ -499,12 +760,12 @@ pub async fn vote_for_review(
     let mut upvoters = (*review
         .upvoters
         .as_array()
-        .ok_or(internal_server_error(voters_parsing_error.to_string()))?)
+        .ok_or_else(|| ApiError::internal(voters_parsing_error))?)
         .clone();
     let mut downvoters = (*review
         .downvoters
         .as_array()
-        .ok_or(internal_server_error(voters_parsing_error.to_string()))?)
+        .ok_or_else(|| ApiError::internal(voters_parsing_error))?)
         .clone();
     let up_pos = upvoters
         .iter()
@@ -512,16 +773,19 @@ pub async fn vote_for_review(
     let down_pos = downvoters
         .iter()
         .position(|downvoter_id| downvoter_id.as_i64().unwrap_or(-1) as i32 == user_info.id);
-    if vote_data.upvote {
+    // 重复投同一票视为撤回
+    let vote = if vote_data.upvote {
         match up_pos {
             None => {
                 upvoters.push(user_info.id.into());
                 if let Some(down_pos) = down_pos {
                     downvoters.swap_remove(down_pos);
                 }
+                "up"
             }
             Some(position) => {
                 upvoters.swap_remove(position);
+                "withdraw"
             }
         }
     } else {
@@ -531,12 +795,14 @@ pub async fn vote_for_review(
                 if let Some(up_pos) = up_pos {
                     upvoters.swap_remove(up_pos);
                 }
+                "down"
             }
             Some(position) => {
                 downvoters.swap_remove(position);
+                "withdraw"
             }
         }
-    }
+    };
     // 更新字段
     let mut updated_review: review::ActiveModel = review.clone().into();
     updated_review.upvoters = Set(Value::Array(upvoters));
@@ -544,15 +810,24 @@ pub async fn vote_for_review(
     let updated_review: Result<review::Model, DbErr> = updated_review.update(db.get_ref()).await;
 
     match updated_review {
-        Ok(updated_review) => Ok(HttpResponse::Ok().json(
-            GetReview::load(updated_review, db.get_ref(), user_info.id)
+        Ok(updated_review) => {
+            metrics::observe_vote(vote);
+            // 评论作者获得的赞数变化，可能达成新的成就
+            if let Err(e) = rules.evaluate(updated_review.reviewer_id, db.get_ref()).await {
+                tracing::warn!(
+                    error = %CauseChain(&e),
+                    user_id = updated_review.reviewer_id,
+                    "unable to evaluate achievement rules"
+                );
+            }
+            let course_id = updated_review.course_id.unwrap_or(-1);
+            let mut updated_review = GetReview::load(updated_review, db.get_ref(), user_info.id)
                 .await
-                .map_err(|e| internal_server_error(e.to_string()))?,
-        )),
-        Err(err) => Err(internal_server_error(format!(
-            "Unable to update the review. Error: {}",
-            err.to_string()
-        ))),
+                .map_err(ApiError::db("Unable to load updated review."))?;
+            pseudonymizer.apply(&mut updated_review, course_id, &user_info);
+            Ok(HttpResponse::Ok().json(updated_review))
+        }
+        Err(err) => Err(ApiError::db("Unable to update the review.")(err).into()),
     }
 }
 
@@ -566,6 +841,7 @@ security(("auth" = []))
 pub async fn get_reviews(
     req: HttpRequest,
     db: web::Data<DatabaseConnection>,
+    pseudonymizer: web::Data<Pseudonymizer>,
 ) -> actix_web::Result<HttpResponse> {
     let user_info = require_authentication(&req).await?;
     let results: Vec<(review::Model, Option<course::Model>)> = Review::find()
@@ -573,45 +849,34 @@ pub async fn get_reviews(
         .find_also_related(Course)
         .all(db.get_ref())
         .await
-        .map_err(|e| internal_server_error(e.to_string()))?;
+        .map_err(ApiError::db("Unable to fetch the reviews."))?;
+    let names = SemesterNames::load(db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to fetch the semesters."))?;
     let mut review_list: Vec<GetMyReview> = vec![];
     for x in results {
         let review = x.0;
         let course = x.1.ok_or_else(|| {
-            internal_server_error(format!(
-                "Unable to find the course of review {}.",
-                review.id
-            ))
+            ApiError::internal(format!("Unable to find the course of review {}.", review.id))
         })?;
 
-        if let Some(group_id) = course.coursegroup_id {
-            review_list.push(GetMyReview::new(
-                review.clone(),
-                course.clone(),
-                group_id,
-                user_info.id,
-            ));
-        } else {
-            review_list.push(GetMyReview::new(
-                review.clone(),
-                course.clone(),
-                -1,
-                user_info.id,
-            ));
-        }
+        let course_id = course.id;
+        let group_id = course.coursegroup_id.unwrap_or(-1);
+        let mut course = GetMultiCourse::from(course);
+        course.set_semester_name(&names);
+        let mut my_review = GetMyReview::new(review, course, group_id, user_info.id);
+        pseudonymizer.apply_to_history(&mut my_review.history, course_id, &user_info);
+        review_list.push(my_review);
     }
 
     Ok(HttpResponse::Ok().json(review_list))
 }
 
-#[derive(Debug, FromQueryResult)]
-pub struct Counts {
-    cnt: i32,
-}
-
 #[utoipa::path(
 responses(
-(status = 200, description = "Get a random review. `is_me` is not included.", body = [GetMyReview])
+(status = 200, description = "Get a random review. `is_me` is not included.", body = GetMyReview),
+(status = 404, description = "There are no reviews yet.", body = ErrorMessage,
+example = json ! (ApiError::NoReviews.to_message())),
 ),
 security(("auth" = []))
 )]
@@ -619,30 +884,18 @@ security(("auth" = []))
 pub async fn get_random_reviews(
     req: HttpRequest,
     db: web::Data<DatabaseConnection>,
+    pseudonymizer: web::Data<Pseudonymizer>,
 ) -> actix_web::Result<HttpResponse> {
     let user_info = require_authentication(&req).await?;
-    let review_count: Option<Counts> = Counts::find_by_statement(Statement::from_string(
-        db.get_ref().get_database_backend(),
-        r#"SELECT COUNT(*) AS cnt FROM review"#.to_string(),
-    ))
-        .one(db.get_ref())
+    // 用 ORM 计数，`COUNT(*)` 在 MySQL 和 Postgres 中返回 BIGINT
+    let review_count = Review::find()
+        .count(db.get_ref())
         .await
-        .map_err(|e| {
-            internal_server_error(format!(
-                "Unable to count the reviews. Error: {}",
-                e.to_string()
-            ))
-        })?;
-
-    let review_count = review_count
-        .ok_or(internal_server_error(format!(
-            "Unable to count the reviews since database returns no result."
-        )))?
-        .cnt;
+        .map_err(ApiError::db("Unable to count the reviews."))?;
     let mut rng = rand::thread_rng();
     // 重试 5 次
     if review_count == 0 {
-        return Err(not_found("No review is found.".to_string()));
+        return Err(ApiError::NoReviews.into());
     }
     for _ in 1..5 {
         let id = rng.gen_range(1..=review_count) as i32;
@@ -656,32 +909,23 @@ pub async fn get_random_reviews(
                 let result = results[0].clone();
 
                 let course = result.1.ok_or_else(|| {
-                    internal_server_error(format!(
-                        "Unable to find the course of review {}.",
-                        result.0.id
-                    ))
+                    ApiError::internal(format!("Unable to find the course of review {}.", result.0.id))
                 })?;
-                return if let Some(course_group_link) = course.coursegroup_id {
-                    Ok(HttpResponse::Ok().json(GetMyReview::new(
-                        result.0.clone(),
-                        course.clone(),
-                        course_group_link,
-                        user_info.id,
-                    )))
-                } else {
-                    Ok(HttpResponse::Ok().json(GetMyReview::new(
-                        result.0,
-                        course.clone(),
-                        -1,
-                        user_info.id,
-                    )))
-                };
+                let course_id = course.id;
+                let course_group_link = course.coursegroup_id.unwrap_or(-1);
+                let names = SemesterNames::load(db.get_ref())
+                    .await
+                    .map_err(ApiError::db("Unable to fetch the semesters."))?;
+                let mut course = GetMultiCourse::from(course);
+                course.set_semester_name(&names);
+                let mut random_review =
+                    GetMyReview::new(result.0, course, course_group_link, user_info.id);
+                pseudonymizer.apply_to_history(&mut random_review.history, course_id, &user_info);
+                return Ok(HttpResponse::Ok().json(random_review));
             }
         }
     }
-    Err(internal_server_error(
-        "Unable to fetch a random review. Retry later.".to_string(),
-    ))
+    Err(ApiError::internal("Unable to fetch a random review. Retry later.").into())
 }
 
 // 将评论标记为删除
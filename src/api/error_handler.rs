@@ -1,41 +1,328 @@
-use actix_web::{HttpResponse, Error};
-use actix_web::error::InternalError;
+use std::fmt;
+use std::sync::OnceLock;
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use actix_web::http::{header, StatusCode};
+use sea_orm::error::{RuntimeErr, SqlxError, SqlxMySqlError};
+use sea_orm::DbErr;
 use serde::{Serialize, Deserialize};
+use serde_json::{json, Map, Value};
+use strum::EnumIter;
 use utoipa::ToSchema;
+use crate::api::i18n::{current_locale, Locale};
+use crate::api::logging::current_request_id;
+
+/// 是否在响应中返回内部错误的详细信息，须在处理请求前设置。未设置时不返回
+static EXPOSE_INTERNAL_ERRORS: OnceLock<bool> = OnceLock::new();
+
+/// 设置是否在响应中返回内部错误的详细信息。只能设置一次，重复设置返回 `false`
+pub fn configure_error_details(expose: bool) -> bool {
+    EXPOSE_INTERNAL_ERRORS.set(expose).is_ok()
+}
+
+/// 错误码。客户端应根据错误码而不是错误信息判断错误的类型
+///
+/// 用 `ErrorCode::iter()` 遍历全部错误码，测试据此检查各语言的翻译是否齐全
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, ToSchema, EnumIter)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    InvalidId,
+    InvalidRequest,
+    InvalidMerge,
+    InvalidUserId,
+    AuthenticationRequired,
+    AuthenticationFailed,
+    AdminRequired,
+    NotReviewOwner,
+    NotDataOwner,
+    RouteNotFound,
+    CourseNotFound,
+    CourseGroupNotFound,
+    ReviewNotFound,
+    NoReviews,
+    UserNotFound,
+    AchievementNotFound,
+    UserAchievementNotFound,
+    SemesterNotFound,
+    DuplicateReview,
+    DuplicateAchievement,
+    DuplicateUserAchievement,
+    DuplicateSemester,
+    RateLimited,
+    DatabaseError,
+    AuthUpstreamError,
+    InternalError,
+}
+
+impl ErrorCode {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ErrorCode::InvalidId
+            | ErrorCode::InvalidRequest
+            | ErrorCode::InvalidMerge
+            | ErrorCode::InvalidUserId => StatusCode::BAD_REQUEST,
+            ErrorCode::AuthenticationRequired | ErrorCode::AuthenticationFailed => StatusCode::UNAUTHORIZED,
+            ErrorCode::AdminRequired | ErrorCode::NotReviewOwner | ErrorCode::NotDataOwner => StatusCode::FORBIDDEN,
+            ErrorCode::RouteNotFound
+            | ErrorCode::CourseNotFound
+            | ErrorCode::CourseGroupNotFound
+            | ErrorCode::ReviewNotFound
+            | ErrorCode::NoReviews
+            | ErrorCode::UserNotFound
+            | ErrorCode::AchievementNotFound
+            | ErrorCode::UserAchievementNotFound
+            | ErrorCode::SemesterNotFound => StatusCode::NOT_FOUND,
+            ErrorCode::DuplicateReview
+            | ErrorCode::DuplicateAchievement
+            | ErrorCode::DuplicateUserAchievement
+            | ErrorCode::DuplicateSemester => StatusCode::CONFLICT,
+            ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::DatabaseError | ErrorCode::AuthUpstreamError | ErrorCode::InternalError => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct ErrorMessage {
+    pub code: ErrorCode,
     pub message: String,
+    /// 与错误相关的字段，如未找到的对象的 id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+    /// 与响应头 `X-Request-Id` 相同，便于在日志中查找
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
-// 预定义了一些常用的错误信息。
-pub fn internal_server_error(error: String) -> Error {
-    InternalError::from_response(error.clone(),
-                                 HttpResponse::InternalServerError().json(ErrorMessage { message: error })).into()
+/// 依次显示错误及其所有 `source`，以 `: ` 分隔
+pub struct CauseChain<'a>(pub &'a (dyn std::error::Error + 'static));
+
+impl fmt::Display for CauseChain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)?;
+        let mut source = self.0.source();
+        while let Some(error) = source {
+            write!(f, ": {}", error)?;
+            source = error.source();
+        }
+        Ok(())
+    }
 }
 
-pub fn not_found(error: String) -> Error {
-    InternalError::from_response(error.clone(),
-                                 HttpResponse::NotFound().json(ErrorMessage { message: error })).into()
+/// 接口返回的错误。`Display` 包含内部错误的全部信息，用于日志；
+/// 返回给客户端的 [`ErrorMessage`] 在生产环境中不包含内部错误的细节
+#[derive(Debug)]
+pub enum ApiError {
+    /// 路径中的 id 不是整数
+    InvalidId { value: String },
+    /// 请求体或查询参数无法解析
+    InvalidRequest { reason: String },
+    /// 课程组不能合并到自身
+    InvalidMerge { group_id: i32 },
+    InvalidUserId { user_id: i32 },
+    AuthenticationRequired,
+    AuthenticationFailed,
+    AdminRequired,
+    NotReviewOwner { review_id: i32 },
+    /// 只有管理员可以操作其他用户的数据
+    NotDataOwner { user_id: i32 },
+    RouteNotFound { path: String },
+    CourseNotFound { id: i32 },
+    CourseGroupNotFound { id: i32 },
+    ReviewNotFound { id: i32 },
+    NoReviews,
+    /// 没有评论和成就的其他用户，视为不存在
+    UserNotFound { user_id: i32 },
+    AchievementNotFound { id: i32 },
+    UserAchievementNotFound { user_id: i32, achievement_id: i32 },
+    SemesterNotFound { id: i32 },
+    DuplicateReview { course_id: i32 },
+    /// 同一领域中成就的名称不能重复
+    DuplicateAchievement { name: String, domain: Option<String> },
+    DuplicateUserAchievement { user_id: i32, achievement_id: i32 },
+    /// 同一学年中学期的编号不能重复
+    DuplicateSemester { year: i32, semester: i32 },
+    RateLimited { retry_after: u64 },
+    Database { context: String, source: DbErr },
+    AuthUpstream { reason: String },
+    Internal { context: String },
 }
 
-pub fn unauthorized(error: String) -> Error {
-    InternalError::from_response(error.clone(),
-                                 HttpResponse::Unauthorized().json(ErrorMessage { message: error })).into()
+impl ApiError {
+    /// 用于 `map_err`，为数据库错误加上说明
+    pub fn db(context: impl Into<String>) -> impl FnOnce(DbErr) -> ApiError {
+        let context = context.into();
+        move |source| ApiError::Database { context, source }
+    }
+
+    pub fn internal(context: impl Into<String>) -> ApiError {
+        ApiError::Internal { context: context.into() }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ApiError::InvalidId { .. } => ErrorCode::InvalidId,
+            ApiError::InvalidRequest { .. } => ErrorCode::InvalidRequest,
+            ApiError::InvalidMerge { .. } => ErrorCode::InvalidMerge,
+            ApiError::InvalidUserId { .. } => ErrorCode::InvalidUserId,
+            ApiError::AuthenticationRequired => ErrorCode::AuthenticationRequired,
+            ApiError::AuthenticationFailed => ErrorCode::AuthenticationFailed,
+            ApiError::AdminRequired => ErrorCode::AdminRequired,
+            ApiError::NotReviewOwner { .. } => ErrorCode::NotReviewOwner,
+            ApiError::NotDataOwner { .. } => ErrorCode::NotDataOwner,
+            ApiError::RouteNotFound { .. } => ErrorCode::RouteNotFound,
+            ApiError::CourseNotFound { .. } => ErrorCode::CourseNotFound,
+            ApiError::CourseGroupNotFound { .. } => ErrorCode::CourseGroupNotFound,
+            ApiError::ReviewNotFound { .. } => ErrorCode::ReviewNotFound,
+            ApiError::NoReviews => ErrorCode::NoReviews,
+            ApiError::UserNotFound { .. } => ErrorCode::UserNotFound,
+            ApiError::AchievementNotFound { .. } => ErrorCode::AchievementNotFound,
+            ApiError::UserAchievementNotFound { .. } => ErrorCode::UserAchievementNotFound,
+            ApiError::SemesterNotFound { .. } => ErrorCode::SemesterNotFound,
+            ApiError::DuplicateReview { .. } => ErrorCode::DuplicateReview,
+            ApiError::DuplicateAchievement { .. } => ErrorCode::DuplicateAchievement,
+            ApiError::DuplicateUserAchievement { .. } => ErrorCode::DuplicateUserAchievement,
+            ApiError::DuplicateSemester { .. } => ErrorCode::DuplicateSemester,
+            ApiError::RateLimited { .. } => ErrorCode::RateLimited,
+            ApiError::Database { .. } => ErrorCode::DatabaseError,
+            ApiError::AuthUpstream { .. } => ErrorCode::AuthUpstreamError,
+            ApiError::Internal { .. } => ErrorCode::InternalError,
+        }
+    }
+
+    fn details(&self) -> Option<Value> {
+        match self {
+            ApiError::InvalidId { value } => Some(json!({ "value": value })),
+            ApiError::InvalidMerge { group_id } => Some(json!({ "group_id": group_id })),
+            ApiError::InvalidUserId { user_id }
+            | ApiError::NotDataOwner { user_id }
+            | ApiError::UserNotFound { user_id } => {
+                Some(json!({ "user_id": user_id }))
+            }
+            ApiError::NotReviewOwner { review_id } => Some(json!({ "review_id": review_id })),
+            ApiError::RouteNotFound { path } => Some(json!({ "path": path })),
+            ApiError::CourseNotFound { id }
+            | ApiError::CourseGroupNotFound { id }
+            | ApiError::ReviewNotFound { id }
+            | ApiError::AchievementNotFound { id }
+            | ApiError::SemesterNotFound { id } => Some(json!({ "id": id })),
+            ApiError::UserAchievementNotFound { user_id, achievement_id }
+            | ApiError::DuplicateUserAchievement { user_id, achievement_id } => {
+                Some(json!({ "user_id": user_id, "achievement_id": achievement_id }))
+            }
+            ApiError::DuplicateReview { course_id } => Some(json!({ "course_id": course_id })),
+            ApiError::DuplicateAchievement { name, domain } => Some(json!({ "name": name, "domain": domain })),
+            ApiError::DuplicateSemester { year, semester } => Some(json!({ "year": year, "semester": semester })),
+            ApiError::RateLimited { retry_after } => Some(json!({ "retry_after": retry_after })),
+            _ => None,
+        }
+    }
+
+    /// 错误信息中的参数，即 `details` 以及请求无法解析的原因
+    fn params(&self) -> Map<String, Value> {
+        let mut params = match self.details() {
+            Some(Value::Object(details)) => details,
+            _ => Map::new(),
+        };
+        if let ApiError::InvalidRequest { reason } = self {
+            params.insert("reason".to_string(), json!(reason));
+        }
+        params
+    }
+
+    fn is_internal(&self) -> bool {
+        self.code().status_code().is_server_error()
+    }
+
+    /// 返回给客户端的错误信息，使用当前请求协商得到的语言。
+    /// 允许返回内部错误的细节时，内部错误的信息与日志相同，不做翻译
+    pub fn to_message(&self) -> ErrorMessage {
+        let expose = self.is_internal() && EXPOSE_INTERNAL_ERRORS.get().copied().unwrap_or(false);
+        ErrorMessage {
+            code: self.code(),
+            message: if expose {
+                self.to_string()
+            } else {
+                current_locale().catalog().error(self.code(), &self.params())
+            },
+            details: self.details(),
+            request_id: current_request_id(),
+        }
+    }
+}
+
+/// 数据库错误是否由唯一索引冲突引起，用于将并发的重复插入转换为对应的业务错误
+pub fn is_unique_violation(error: &DbErr) -> bool {
+    let (DbErr::Exec(RuntimeErr::SqlxError(SqlxError::Database(error)))
+    | DbErr::Query(RuntimeErr::SqlxError(SqlxError::Database(error)))) = error
+    else {
+        return false;
+    };
+    if let Some(error) = error.try_downcast_ref::<SqlxMySqlError>() {
+        // ER_DUP_ENTRY
+        return error.number() == 1062;
+    }
+    // Postgres 的 unique_violation，SQLite 的 SQLITE_CONSTRAINT_UNIQUE 和 SQLITE_CONSTRAINT_PRIMARYKEY
+    matches!(error.code().as_deref(), Some("23505" | "2067" | "1555"))
 }
 
+/// 请求体、查询参数和路径参数无法解析时返回的错误
+pub fn extractor_error(error: impl fmt::Display) -> actix_web::Error {
+    ApiError::InvalidRequest {
+        reason: error.to_string(),
+    }
+    .into()
+}
 
-pub fn bad_request(error: String) -> Error {
-    InternalError::from_response(error.clone(),
-                                 HttpResponse::BadRequest().json(ErrorMessage { message: error })).into()
+/// 未匹配到任何路由时返回的错误
+pub async fn route_not_found(req: HttpRequest) -> Result<HttpResponse, ApiError> {
+    Err(ApiError::RouteNotFound {
+        path: req.path().to_string(),
+    })
 }
 
-pub fn conflict(error: String) -> Error {
-    InternalError::from_response(error.clone(),
-                                 HttpResponse::Conflict().json(ErrorMessage { message: error })).into()
+/// 解析路径中名为 `name` 的 id
+pub fn path_id(req: &HttpRequest, name: &str) -> Result<i32, ApiError> {
+    let value = req.match_info().query(name);
+    value.parse::<i32>().map_err(|_| ApiError::InvalidId {
+        value: value.to_string(),
+    })
 }
 
-pub fn forbidden(error: String) -> Error {
-    InternalError::from_response(error.clone(),
-                                 HttpResponse::Forbidden().json(ErrorMessage { message: error })).into()
-}
\ No newline at end of file
+/// 用于日志。内部错误包含全部细节，其他错误与英文的错误信息相同
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Database { context, source } => write!(f, "{} Error: {}", context, CauseChain(source)),
+            ApiError::AuthUpstream { reason } => {
+                write!(f, "Cannot validate authorization information. {}", reason)
+            }
+            ApiError::Internal { context } => write!(f, "{}", context),
+            _ => write!(f, "{}", Locale::En.catalog().error(self.code(), &self.params())),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::Database { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        self.code().status_code()
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let ApiError::RateLimited { retry_after } = self {
+            response.insert_header((header::RETRY_AFTER, *retry_after));
+        }
+        response.json(self.to_message())
+    }
+}
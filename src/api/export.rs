@@ -0,0 +1,382 @@
+use std::future::Future;
+use actix_web::http::header;
+use actix_web::web::Bytes;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use entity::prelude::*;
+use entity::review::ERASED_REVIEWER_ID;
+use entity::{course, coursegroup, review};
+use futures_util::stream::{self, Stream};
+use rand::Rng;
+use sea_orm::sea_query::Query;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use utoipa::{IntoParams, ToSchema};
+use crate::api::auth::require_authentication;
+use crate::api::error_handler::ApiError;
+
+/// 匿名化导出时使用的盐
+pub const EXPORT_SALT_HEADER: &str = "X-Export-Salt";
+
+/// 每次从数据库读取的行数。导出占用的内存只与之相关，与表的大小无关
+const PAGE_SIZE: u64 = 500;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// 每行一个 JSON 对象
+    #[default]
+    Ndjson,
+    Csv,
+}
+
+impl ExportFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportOptions {
+    /// `ndjson` (default) or `csv`.
+    #[serde(default)]
+    #[param(value_type = Option<String>)]
+    pub format: ExportFormat,
+    /// Only export courses in this year. For groups, only those with a course in this year; for reviews, only those of such courses.
+    pub year: Option<i32>,
+    /// Only export courses in this semester. Applies to groups and reviews the same way as `year`.
+    pub semester: Option<i32>,
+    /// Only export courses (groups, reviews of courses) of this department.
+    pub department: Option<String>,
+    /// Replace reviewer ids with a salted hash. Defaults to false. The salt is given in the `X-Export-Salt` header.
+    #[serde(default)]
+    pub anonymize: bool,
+    /// Also export reviews whose author erased their data. Defaults to false. Reviews have no deleted or folded state, so this is the only review exclusion.
+    #[serde(default)]
+    pub include_erased: bool,
+}
+
+impl ExportOptions {
+    fn course_condition(&self) -> Condition {
+        let mut condition = Condition::all();
+        if let Some(year) = self.year {
+            condition = condition.add(course::Column::Year.eq(year));
+        }
+        if let Some(semester) = self.semester {
+            condition = condition.add(course::Column::Semester.eq(semester));
+        }
+        if let Some(department) = &self.department {
+            condition = condition.add(course::Column::Department.eq(department.clone()));
+        }
+        condition
+    }
+
+    fn group_condition(&self) -> Condition {
+        let mut condition = Condition::all();
+        if let Some(department) = &self.department {
+            condition = condition.add(coursegroup::Column::Department.eq(department.clone()));
+        }
+        if self.year.is_some() || self.semester.is_some() {
+            let mut semester_condition = Condition::all();
+            if let Some(year) = self.year {
+                semester_condition = semester_condition.add(course::Column::Year.eq(year));
+            }
+            if let Some(semester) = self.semester {
+                semester_condition = semester_condition.add(course::Column::Semester.eq(semester));
+            }
+            condition = condition.add(coursegroup::Column::Id.in_subquery(
+                Query::select()
+                    .column(course::Column::CoursegroupId)
+                    .from(course::Entity)
+                    .cond_where(semester_condition)
+                    .to_owned(),
+            ));
+        }
+        condition
+    }
+
+    fn review_condition(&self) -> Condition {
+        let mut condition = Condition::all();
+        if self.year.is_some() || self.semester.is_some() || self.department.is_some() {
+            condition = condition.add(review::Column::CourseId.in_subquery(
+                Query::select()
+                    .column(course::Column::Id)
+                    .from(course::Entity)
+                    .cond_where(self.course_condition())
+                    .to_owned(),
+            ));
+        }
+        if !self.include_erased {
+            condition = condition.add(review::Column::ReviewerId.ne(ERASED_REVIEWER_ID));
+        }
+        condition
+    }
+}
+
+/// 导出的评论。`rank` 编码为 JSON 字符串，以便放入 CSV 的单元格
+#[derive(Serialize, Debug, Clone)]
+pub struct ReviewRow {
+    pub id: i32,
+    pub course_id: Option<i32>,
+    /// 评论者 id，或匿名化后的哈希
+    pub reviewer: String,
+    pub title: String,
+    pub content: String,
+    pub rank: String,
+    pub upvotes: usize,
+    pub downvotes: usize,
+    pub time_created: String,
+    pub time_updated: String,
+}
+
+/// 以加盐哈希替换评论者 id。同一次导出中同一用户的哈希相同
+struct Anonymizer {
+    salt: Option<String>,
+}
+
+impl Anonymizer {
+    /// 未指定盐时使用随机的盐，使不同的导出无法相互关联
+    fn new(options: &ExportOptions, salt: Option<String>) -> Self {
+        let salt = options.anonymize.then(|| {
+            salt.unwrap_or_else(|| {
+                let bytes: [u8; 16] = rand::thread_rng().gen();
+                base16ct::lower::encode_string(&bytes)
+            })
+        });
+        Anonymizer { salt }
+    }
+
+    fn reviewer(&self, reviewer_id: i32) -> String {
+        match &self.salt {
+            Some(salt) => {
+                let mut hasher = Sha3_256::new();
+                hasher.update(salt.as_bytes());
+                hasher.update(reviewer_id.to_be_bytes());
+                base16ct::lower::encode_string(&hasher.finalize())[..16].to_string()
+            }
+            None => reviewer_id.to_string(),
+        }
+    }
+
+    fn review_row(&self, model: review::Model) -> ReviewRow {
+        let count = |voters: &serde_json::Value| voters.as_array().map_or(0, |v| v.len());
+        ReviewRow {
+            id: model.id,
+            course_id: model.course_id,
+            reviewer: self.reviewer(model.reviewer_id),
+            title: model.title,
+            content: model.content,
+            rank: model.rank.to_string(),
+            upvotes: count(&model.upvoters),
+            downvotes: count(&model.downvoters),
+            time_created: model.time_created.to_string(),
+            time_updated: model.time_updated.to_string(),
+        }
+    }
+}
+
+fn encode<T: Serialize>(rows: &[T], format: ExportFormat, with_header: bool) -> Result<Bytes, String> {
+    match format {
+        ExportFormat::Ndjson => {
+            let mut buffer = vec![];
+            for row in rows {
+                serde_json::to_writer(&mut buffer, row).map_err(|e| e.to_string())?;
+                buffer.push(b'\n');
+            }
+            Ok(buffer.into())
+        }
+        ExportFormat::Csv => {
+            let mut writer = csv::WriterBuilder::new()
+                .has_headers(with_header)
+                .from_writer(vec![]);
+            for row in rows {
+                writer.serialize(row).map_err(|e| e.to_string())?;
+            }
+            writer.into_inner().map(Bytes::from).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// 按主键分页读取并逐页编码，使导出占用的内存不随表的大小增长。
+/// `fetch_page` 读取主键大于给定值的下一页，返回每行的主键和导出的内容。
+fn export_stream<T, F, Fut>(
+    format: ExportFormat,
+    fetch_page: F,
+) -> impl Stream<Item = Result<Bytes, actix_web::Error>>
+where
+    T: Serialize,
+    F: Fn(i32) -> Fut,
+    Fut: Future<Output = Result<Vec<(i32, T)>, DbErr>>,
+{
+    // 状态：(上一页最后一行的主键, 是否为第一页)，读完后为 None
+    stream::try_unfold(Some((0, true)), move |state| {
+        let page = state.map(|(after_id, first)| (fetch_page(after_id), first));
+        async move {
+            let Some((page, first)) = page else {
+                return Ok(None);
+            };
+            let rows = page.await.map_err(ApiError::db("Unable to export data."))?;
+            let next = match rows.last() {
+                Some((last_id, _)) if rows.len() as u64 == PAGE_SIZE => Some((*last_id, false)),
+                _ => None,
+            };
+            let rows: Vec<T> = rows.into_iter().map(|(_, row)| row).collect();
+            let bytes = encode(&rows, format, first).map_err(|e| {
+                ApiError::internal(format!("Unable to encode exported data. Error: {}", e))
+            })?;
+            Ok(Some((bytes, next)))
+        }
+    })
+}
+
+fn streaming_response<S>(name: &str, format: ExportFormat, stream: S) -> HttpResponse
+where
+    S: Stream<Item = Result<Bytes, actix_web::Error>> + 'static,
+{
+    HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.{}\"", name, format.extension()),
+        ))
+        .streaming(stream)
+}
+
+async fn require_admin(req: &HttpRequest) -> actix_web::Result<()> {
+    let user_info = require_authentication(req).await?;
+    user_info.require_admin()?;
+    Ok(())
+}
+
+#[utoipa::path(
+params(ExportOptions),
+responses(
+(status = 200, description = "All courses matching the filters, streamed as NDJSON or CSV. Every row is a course with `coursegroup_id`."),
+(status = 403, description = "Only admin can export data.", body = ErrorMessage),
+),
+security(("auth" = []))
+)]
+#[get("/export/courses")]
+pub async fn export_courses(
+    options: web::Query<ExportOptions>,
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    require_admin(&req).await?;
+    let options = options.into_inner();
+    let format = options.format;
+    let db = db.get_ref().clone();
+    let stream = export_stream(format, move |after_id| {
+        let (db, condition) = (db.clone(), options.course_condition());
+        async move {
+            let courses: Vec<course::Model> = Course::find()
+                .filter(condition)
+                .filter(course::Column::Id.gt(after_id))
+                .order_by_asc(course::Column::Id)
+                .limit(PAGE_SIZE)
+                .all(&db)
+                .await?;
+            Ok(courses.into_iter().map(|course| (course.id, course)).collect())
+        }
+    });
+    Ok(streaming_response("courses", format, stream))
+}
+
+#[utoipa::path(
+params(ExportOptions),
+responses(
+(status = 200, description = "All course groups matching the filters, streamed as NDJSON or CSV. Courses are not included."),
+(status = 403, description = "Only admin can export data.", body = ErrorMessage),
+),
+security(("auth" = []))
+)]
+#[get("/export/groups")]
+pub async fn export_groups(
+    options: web::Query<ExportOptions>,
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    require_admin(&req).await?;
+    let options = options.into_inner();
+    let format = options.format;
+    let db = db.get_ref().clone();
+    let stream = export_stream(format, move |after_id| {
+        let (db, condition) = (db.clone(), options.group_condition());
+        async move {
+            let groups: Vec<coursegroup::Model> = Coursegroup::find()
+                .filter(condition)
+                .filter(coursegroup::Column::Id.gt(after_id))
+                .order_by_asc(coursegroup::Column::Id)
+                .limit(PAGE_SIZE)
+                .all(&db)
+                .await?;
+            Ok(groups.into_iter().map(|group| (group.id, group)).collect())
+        }
+    });
+    Ok(streaming_response("groups", format, stream))
+}
+
+#[utoipa::path(
+params(
+ExportOptions,
+("X-Export-Salt" = Option<String>, Header, description = "The salt for `anonymize`. A random salt is used if not given, so that different exports cannot be linked."),
+),
+responses(
+(status = 200, description = "All reviews matching the filters, streamed as NDJSON or CSV."),
+(status = 403, description = "Only admin can export data.", body = ErrorMessage),
+),
+security(("auth" = []))
+)]
+#[get("/export/reviews")]
+pub async fn export_reviews(
+    options: web::Query<ExportOptions>,
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    require_admin(&req).await?;
+    let options = options.into_inner();
+    let format = options.format;
+    // 盐不放在查询参数中，以免出现在访问日志里
+    let salt = req
+        .headers()
+        .get(EXPORT_SALT_HEADER)
+        .map(|salt| {
+            salt.to_str().map(str::to_owned).map_err(|_| ApiError::InvalidRequest {
+                reason: format!("Invalid `{}` header.", EXPORT_SALT_HEADER),
+            })
+        })
+        .transpose()?;
+    let anonymizer = std::sync::Arc::new(Anonymizer::new(&options, salt));
+    let db = db.get_ref().clone();
+    let stream = export_stream(format, move |after_id| {
+        let (db, condition, anonymizer) = (db.clone(), options.review_condition(), anonymizer.clone());
+        async move {
+            let reviews: Vec<review::Model> = Review::find()
+                .filter(condition)
+                .filter(review::Column::Id.gt(after_id))
+                .order_by_asc(review::Column::Id)
+                .limit(PAGE_SIZE)
+                .all(&db)
+                .await?;
+            Ok(reviews
+                .into_iter()
+                .map(|review| (review.id, anonymizer.review_row(review)))
+                .collect())
+        }
+    });
+    Ok(streaming_response("reviews", format, stream))
+}
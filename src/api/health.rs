@@ -0,0 +1,141 @@
+use std::time::Duration;
+use actix_web::{get, web, HttpResponse};
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{ConnectionTrait, DatabaseConnection, Statement};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::api::curriculum_board::{is_course_group_cache_built, is_course_group_cache_warm};
+use crate::api::error_handler::CauseChain;
+use crate::settings::{AuthMode, Settings};
+
+/// 检查认证服务时的超时时间
+const AUTH_UPSTREAM_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct HealthCheck {
+    pub ok: bool,
+    /// 未通过时的原因，或通过时的补充信息。接口无需认证，因此不包含内部错误的细节，细节只记录在日志中
+    pub detail: Option<String>,
+}
+
+impl HealthCheck {
+    fn pass(detail: Option<String>) -> Self {
+        HealthCheck { ok: true, detail }
+    }
+
+    fn fail(detail: String) -> Self {
+        HealthCheck {
+            ok: false,
+            detail: Some(detail),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct Readiness {
+    pub ready: bool,
+    pub database: HealthCheck,
+    pub migrations: HealthCheck,
+    pub course_cache: HealthCheck,
+    /// 仅在 `auth.readiness_check` 开启时检查
+    pub auth_upstream: Option<HealthCheck>,
+}
+
+async fn check_database(db: &DatabaseConnection) -> HealthCheck {
+    let backend = db.get_database_backend();
+    match db.execute(Statement::from_string(backend, "SELECT 1".to_string())).await {
+        Ok(_) => HealthCheck::pass(None),
+        Err(e) => {
+            tracing::error!(error = %CauseChain(&e), "readiness check of the database failed");
+            HealthCheck::fail("The database is unreachable.".to_string())
+        }
+    }
+}
+
+async fn check_migrations(db: &DatabaseConnection) -> HealthCheck {
+    match Migrator::get_pending_migrations(db).await {
+        Ok(pending) if pending.is_empty() => HealthCheck::pass(None),
+        Ok(pending) => HealthCheck::fail(format!("{} migrations are pending.", pending.len())),
+        Err(e) => {
+            tracing::error!(error = %CauseChain(&e), "readiness check of the migrations failed");
+            HealthCheck::fail("Unable to read the migration status.".to_string())
+        }
+    }
+}
+
+/// 只报告缓存的状态，不构建缓存。缓存由启动时的后台任务预热，
+/// 之后在课程变化时被清空并在下次请求时重建，此时仍视为就绪，避免就绪状态随之反复
+fn check_course_cache() -> HealthCheck {
+    if !is_course_group_cache_built() {
+        HealthCheck::fail("The cache is being warmed up.".to_string())
+    } else if is_course_group_cache_warm() {
+        HealthCheck::pass(None)
+    } else {
+        HealthCheck::pass(Some("The cache is cold and will be rebuilt on the next request.".to_string()))
+    }
+}
+
+/// 认证服务能返回任何 HTTP 响应（包括 401）即视为可达
+async fn check_auth_upstream(url: &str) -> HealthCheck {
+    let client = reqwest::Client::new();
+    match client.get(url).timeout(AUTH_UPSTREAM_TIMEOUT).send().await {
+        Ok(_) => HealthCheck::pass(None),
+        Err(e) => {
+            tracing::error!(error = %CauseChain(&e), "readiness check of the auth upstream failed");
+            HealthCheck::fail("The auth upstream is unreachable.".to_string())
+        }
+    }
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "The server is alive"),
+)
+)]
+#[get("/healthz")]
+pub async fn liveness() -> HttpResponse {
+    HttpResponse::Ok().body("ok")
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "The server is ready to serve requests", body = Readiness),
+(status = 503, description = "Some checks failed", body = Readiness),
+)
+)]
+#[get("/readyz")]
+pub async fn readiness(
+    db: web::Data<DatabaseConnection>,
+    settings: Option<web::Data<Settings>>,
+) -> HttpResponse {
+    let database = check_database(db.get_ref()).await;
+    let migrations = if database.ok {
+        check_migrations(db.get_ref()).await
+    } else {
+        HealthCheck::fail("Skipped because the database is unavailable.".to_string())
+    };
+    let course_cache = check_course_cache();
+    let auth_upstream = match settings.as_deref().map(|settings| &settings.auth) {
+        Some(auth) if auth.readiness_check && auth.mode == AuthMode::Remote => {
+            Some(check_auth_upstream(auth.url.as_deref().unwrap_or_default()).await)
+        }
+        _ => None,
+    };
+
+    let ready = database.ok
+        && migrations.ok
+        && course_cache.ok
+        && auth_upstream.as_ref().is_none_or(|check| check.ok);
+    let readiness = Readiness {
+        ready,
+        database,
+        migrations,
+        course_cache,
+        auth_upstream,
+    };
+    if ready {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
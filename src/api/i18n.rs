@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{AcceptLanguage, Header, Preference};
+use actix_web::middleware::Next;
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use strum::IntoEnumIterator;
+use crate::api::error_handler::ErrorCode;
+
+tokio::task_local! {
+    static LOCALE: Locale;
+}
+
+/// 支持的语言。请求未指定或指定的语言均不支持时，使用英文
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    ZhCn,
+    #[default]
+    En,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::ZhCn, Locale::En];
+
+    /// 按 `Accept-Language` 中的权重依次匹配，`zh-TW` 等其他中文变体也使用简体中文
+    pub fn negotiate(accept_language: Option<&AcceptLanguage>) -> Locale {
+        let Some(accept_language) = accept_language else {
+            return Locale::default();
+        };
+        for preference in accept_language.ranked() {
+            match preference {
+                Preference::Specific(tag) => match tag.primary_language() {
+                    "zh" => return Locale::ZhCn,
+                    "en" => return Locale::En,
+                    _ => continue,
+                },
+                Preference::Any => return Locale::default(),
+            }
+        }
+        Locale::default()
+    }
+
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Locale::ZhCn => "zh-CN",
+            Locale::En => "en",
+        }
+    }
+
+    pub fn catalog(&self) -> &'static Catalog {
+        match self {
+            Locale::ZhCn => &ZH_CN,
+            Locale::En => &EN,
+        }
+    }
+}
+
+/// 某一语言的全部文本。文本中的 `{name}` 会被替换为同名参数的值
+#[derive(Debug, Deserialize)]
+pub struct Catalog {
+    /// 首页的欢迎信息
+    pub hello: String,
+    /// 首页中 Swagger UI 的地址，只在开启时显示
+    pub docs: String,
+    /// 以错误码为键的错误信息
+    pub errors: HashMap<ErrorCode, String>,
+}
+
+lazy_static! {
+    static ref ZH_CN: Catalog = toml::from_str(include_str!("../../locales/zh-CN.toml"))
+        .expect("Unable to parse the zh-CN message catalog.");
+    static ref EN: Catalog = toml::from_str(include_str!("../../locales/en.toml"))
+        .expect("Unable to parse the en message catalog.");
+}
+
+impl Catalog {
+    /// 缺少翻译的错误码
+    pub fn missing_errors(&self) -> Vec<ErrorCode> {
+        ErrorCode::iter()
+            .filter(|code| !self.errors.contains_key(code))
+            .collect()
+    }
+
+    /// 错误码对应的信息。缺少翻译时使用英文，仍然缺少时直接返回错误码
+    pub fn error(&self, code: ErrorCode, params: &Map<String, Value>) -> String {
+        match self.errors.get(&code).or_else(|| EN.errors.get(&code)) {
+            Some(template) => render(template, params),
+            None => serde_json::to_value(code)
+                .ok()
+                .and_then(|code| code.as_str().map(str::to_string))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+pub fn render(template: &str, params: &Map<String, Value>) -> String {
+    let mut text = template.to_string();
+    for (name, value) in params {
+        let value = match value {
+            Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        text = text.replace(&format!("{{{}}}", name), &value);
+    }
+    text
+}
+
+/// 当前请求协商得到的语言。不在请求中时返回默认语言
+pub fn current_locale() -> Locale {
+    LOCALE.try_with(|locale| *locale).unwrap_or_default()
+}
+
+/// 根据 `Accept-Language` 选择语言，供处理函数和错误信息使用
+pub async fn negotiate_locale(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let accept_language = AcceptLanguage::parse(&req).ok();
+    let locale = Locale::negotiate(accept_language.as_ref());
+    LOCALE.scope(locale, next.call(req)).await
+}
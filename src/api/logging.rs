@@ -0,0 +1,86 @@
+use std::time::Instant;
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::HttpMessage;
+use rand::RngCore;
+use crate::api::auth::UserInfo;
+use crate::api::metrics;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// 当前请求的 id。不在请求中时返回 `None`
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// 沿用上游（如反向代理）传入的合法请求 id，否则生成一个新的
+fn request_id(req: &ServiceRequest) -> String {
+    let upstream = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= 64
+                && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        });
+    match upstream {
+        Some(id) => id.to_string(),
+        None => {
+            let mut bytes = [0u8; 16];
+            rand::thread_rng().fill_bytes(&mut bytes);
+            base16ct::lower::encode_string(&bytes)
+        }
+    }
+}
+
+/// 为每个请求分配 id 并写入响应头，请求结束后记录一条结构化日志，并更新请求数与延迟的监控指标。
+///
+/// 处理函数中通过 [`require_authentication`](crate::api::auth::require_authentication)
+/// 验证的用户会一并记录；5xx 响应会记录其错误信息。
+pub async fn log_request(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let id = request_id(&req);
+    let start = Instant::now();
+    let method = req.method().to_string();
+    let pattern = req.match_pattern();
+    let route = pattern.clone().unwrap_or_else(|| req.path().to_string());
+    let metrics_route = pattern.unwrap_or_else(|| metrics::UNMATCHED_ROUTE.to_string());
+
+    let result = REQUEST_ID.scope(id.clone(), next.call(req)).await;
+    let latency = start.elapsed();
+    let latency_ms = latency.as_secs_f64() * 1000.0;
+    let mut res = match result {
+        Ok(res) => res.map_into_boxed_body(),
+        Err(error) => {
+            // 内层中间件返回的错误没有对应的请求，无法添加响应头，只记录日志
+            let status = error.as_response_error().status_code().as_u16();
+            metrics::observe_request(&method, &metrics_route, status, latency);
+            tracing::error!(request_id = %id, method, route, status, latency_ms, error = %error, "request failed");
+            return Err(error);
+        }
+    };
+
+    let status = res.status().as_u16();
+    metrics::observe_request(&method, &metrics_route, status, latency);
+    let user_id = res.request().extensions().get::<UserInfo>().map(|user| user.id);
+    if res.status().is_server_error() {
+        let error = res.response().error().map(|e| e.to_string());
+        tracing::error!(request_id = %id, method, route, status, latency_ms, user_id, error, "request failed");
+    } else {
+        tracing::info!(request_id = %id, method, route, status, latency_ms, user_id, "request");
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    Ok(res)
+}
@@ -0,0 +1,142 @@
+use std::time::Duration;
+use actix_web::{get, HttpResponse};
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    register_int_gauge, Encoder, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    TextEncoder,
+};
+use crate::api::error_handler::ApiError;
+
+/// 未匹配到任何路由的请求，统一记为此路由，避免路径作为标签导致序列数量无限增长
+pub const UNMATCHED_ROUTE: &str = "<unmatched>";
+
+lazy_static! {
+    static ref HTTP_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "http_requests_total",
+        "Number of HTTP requests by route and status.",
+        &["method", "route", "status"]
+    )
+    .unwrap();
+    static ref HTTP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "http_request_duration_seconds",
+        "HTTP request latency by route.",
+        &["method", "route"]
+    )
+    .unwrap();
+    static ref DB_QUERY_DURATION: HistogramVec = register_histogram_vec!(
+        "db_query_duration_seconds",
+        "Database query latency by statement type.",
+        &["operation"],
+        vec![0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]
+    )
+    .unwrap();
+    static ref DB_QUERY_FAILURES: IntCounterVec = register_int_counter_vec!(
+        "db_query_failures_total",
+        "Number of failed database queries by statement type.",
+        &["operation"]
+    )
+    .unwrap();
+    static ref CACHE_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "cache_requests_total",
+        "Number of cache lookups by cache and result (hit or miss).",
+        &["cache", "result"]
+    )
+    .unwrap();
+    static ref AUTH_UPSTREAM_DURATION: Histogram = register_histogram!(
+        "auth_upstream_duration_seconds",
+        "Latency of requests to the authentication service."
+    )
+    .unwrap();
+    static ref AUTH_UPSTREAM_FAILURES: IntCounterVec = register_int_counter_vec!(
+        "auth_upstream_failures_total",
+        "Number of failed requests to the authentication service by reason.",
+        &["reason"]
+    )
+    .unwrap();
+    static ref COURSE_CACHE_REBUILD_DURATION: Histogram = register_histogram!(
+        "course_cache_rebuild_duration_seconds",
+        "Time spent rebuilding the course group cache."
+    )
+    .unwrap();
+    static ref COURSE_CACHE_SIZE: IntGauge = register_int_gauge!(
+        "course_cache_size_bytes",
+        "Total size of the serialized course group caches of all filters."
+    )
+    .unwrap();
+    static ref REVIEWS_CREATED: IntCounter =
+        register_int_counter!("reviews_created_total", "Number of reviews created.").unwrap();
+    static ref REVIEW_VOTES: IntCounterVec = register_int_counter_vec!(
+        "review_votes_total",
+        "Number of votes on reviews by vote (up, down or withdraw).",
+        &["vote"]
+    )
+    .unwrap();
+}
+
+pub fn observe_request(method: &str, route: &str, status: u16, latency: Duration) {
+    HTTP_REQUESTS
+        .with_label_values(&[method, route, &status.to_string()])
+        .inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[method, route])
+        .observe(latency.as_secs_f64());
+}
+
+/// 作为数据库连接的 metric callback，按语句类型（SELECT、INSERT 等）记录耗时
+pub fn observe_db_query(info: &sea_orm::metric::Info<'_>) {
+    let operation = info
+        .statement
+        .sql
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    DB_QUERY_DURATION
+        .with_label_values(&[&operation])
+        .observe(info.elapsed.as_secs_f64());
+    if info.failed {
+        DB_QUERY_FAILURES.with_label_values(&[&operation]).inc();
+    }
+}
+
+pub fn observe_cache(cache: &str, hit: bool) {
+    CACHE_REQUESTS
+        .with_label_values(&[cache, if hit { "hit" } else { "miss" }])
+        .inc();
+}
+
+pub fn observe_auth_upstream(latency: Duration) {
+    AUTH_UPSTREAM_DURATION.observe(latency.as_secs_f64());
+}
+
+/// `reason` 为 `request`（无法连接）或 `response`（无法解析返回的用户信息）
+pub fn observe_auth_upstream_failure(reason: &str) {
+    AUTH_UPSTREAM_FAILURES.with_label_values(&[reason]).inc();
+}
+
+pub fn observe_course_cache_rebuild(duration: Duration, size: usize) {
+    COURSE_CACHE_REBUILD_DURATION.observe(duration.as_secs_f64());
+    COURSE_CACHE_SIZE.set(size as i64);
+}
+
+pub fn observe_review_created() {
+    REVIEWS_CREATED.inc();
+}
+
+pub fn observe_vote(vote: &str) {
+    REVIEW_VOTES.with_label_values(&[vote]).inc();
+}
+
+/// 以 Prometheus 的文本格式返回指标。只在 `server.metrics_bind` 上提供，不在 API 的地址上注册
+#[get("/metrics")]
+pub async fn metrics() -> actix_web::Result<HttpResponse> {
+    let encoder = TextEncoder::new();
+    let mut buffer = vec![];
+    encoder
+        .encode(&prometheus::gather(), &mut buffer)
+        .map_err(|e| ApiError::internal(format!("Unable to encode metrics. Error: {}", e)))?;
+    Ok(HttpResponse::Ok()
+        .content_type(encoder.format_type())
+        .body(buffer))
+}
@@ -1,4 +1,15 @@
+pub mod achievement;
 pub mod curriculum_board;
+pub mod export;
+pub mod health;
+pub mod i18n;
+pub mod logging;
+pub mod metrics;
+pub mod negotiation;
+pub mod personal_data;
 pub mod r#static;
-mod auth;
-mod error_handler;
+pub mod rate_limit;
+pub mod semester;
+pub mod user;
+pub(crate) mod auth;
+pub mod error_handler;
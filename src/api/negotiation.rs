@@ -0,0 +1,141 @@
+use std::io::{self, Write};
+use actix_web::http::header::{self, AcceptEncoding, Encoding, Header};
+use actix_web::{HttpRequest, HttpResponse};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use crate::api::error_handler::ApiError;
+
+/// Brotli 的压缩等级与窗口大小。内容只在缓存重建时压缩一次，压缩率优先
+const BROTLI_QUALITY: u32 = 9;
+const BROTLI_LG_WINDOW_SIZE: u32 = 22;
+
+/// 响应体的序列化格式，按请求的 `Accept` 选择，默认为 JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResponseFormat {
+    Json,
+    MessagePack,
+    Cbor,
+}
+
+impl ResponseFormat {
+    pub const ALL: [ResponseFormat; 3] = [ResponseFormat::Json, ResponseFormat::MessagePack, ResponseFormat::Cbor];
+
+    /// 依次匹配 `Accept` 中权重最高的类型，都不支持时使用 JSON
+    pub fn negotiate(req: &HttpRequest) -> ResponseFormat {
+        let Ok(accept) = header::Accept::parse(req) else {
+            return ResponseFormat::Json;
+        };
+        accept
+            .ranked()
+            .iter()
+            .find_map(|mime| match mime.essence_str() {
+                "application/json" | "application/*" | "*/*" => Some(ResponseFormat::Json),
+                "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                    Some(ResponseFormat::MessagePack)
+                }
+                "application/cbor" => Some(ResponseFormat::Cbor),
+                _ => None,
+            })
+            .unwrap_or(ResponseFormat::Json)
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ResponseFormat::Json => "application/json",
+            ResponseFormat::MessagePack => "application/msgpack",
+            ResponseFormat::Cbor => "application/cbor",
+        }
+    }
+
+    /// MessagePack 以字段名作为键，与 JSON 的结构相同
+    pub fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, String> {
+        match self {
+            ResponseFormat::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            ResponseFormat::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+            ResponseFormat::Cbor => {
+                let mut buffer = vec![];
+                ciborium::into_writer(value, &mut buffer).map_err(|e| e.to_string())?;
+                Ok(buffer)
+            }
+        }
+    }
+
+    /// 按此格式返回 `value`，交由 `Compress` 中间件压缩
+    pub fn respond<T: Serialize + ?Sized>(&self, value: &T) -> Result<HttpResponse, ApiError> {
+        let body = self
+            .encode(value)
+            .map_err(|e| ApiError::internal(format!("Unable to encode the response. Error: {}", e)))?;
+        Ok(HttpResponse::Ok()
+            .content_type(self.content_type())
+            .insert_header((header::VARY, "Accept"))
+            .body(body))
+    }
+}
+
+/// 预先压缩的内容编码，按请求的 `Accept-Encoding` 选择
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentCoding {
+    Identity,
+    Gzip,
+    Brotli,
+}
+
+impl ContentCoding {
+    pub const ALL: [ContentCoding; 3] = [ContentCoding::Identity, ContentCoding::Gzip, ContentCoding::Brotli];
+
+    /// 在 `available` 中选择，`identity` 总是可用
+    pub fn negotiate(req: &HttpRequest, available: &[ContentCoding]) -> ContentCoding {
+        let supported: Vec<Encoding> = [ContentCoding::Brotli, ContentCoding::Gzip]
+            .into_iter()
+            .filter(|coding| available.contains(coding))
+            .map(|coding| coding.encoding())
+            .chain([Encoding::identity()])
+            .collect();
+        let encoding = AcceptEncoding::parse(req)
+            .ok()
+            .and_then(|accept_encoding| accept_encoding.negotiate(supported.iter()));
+        match encoding {
+            Some(encoding) if encoding == Encoding::brotli() => ContentCoding::Brotli,
+            Some(encoding) if encoding == Encoding::gzip() => ContentCoding::Gzip,
+            _ => ContentCoding::Identity,
+        }
+    }
+
+    fn encoding(&self) -> Encoding {
+        match self {
+            ContentCoding::Identity => Encoding::identity(),
+            ContentCoding::Gzip => Encoding::gzip(),
+            ContentCoding::Brotli => Encoding::brotli(),
+        }
+    }
+
+    /// `Content-Encoding` 的值。`identity` 同时使 `Compress` 中间件不再压缩
+    pub fn header_value(&self) -> &'static str {
+        match self {
+            ContentCoding::Identity => "identity",
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Brotli => "br",
+        }
+    }
+
+    pub fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            ContentCoding::Identity => Ok(data.to_vec()),
+            ContentCoding::Gzip => {
+                let mut encoder = GzEncoder::new(vec![], Compression::best());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            ContentCoding::Brotli => {
+                let mut compressed = vec![];
+                let mut writer =
+                    brotli::CompressorWriter::new(&mut compressed, 4096, BROTLI_QUALITY, BROTLI_LG_WINDOW_SIZE);
+                writer.write_all(data)?;
+                writer.flush()?;
+                drop(writer);
+                Ok(compressed)
+            }
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use crate::api::auth::{require_authentication, UserInfo};
+use crate::api::error_handler::{path_id, ApiError};
+use actix_web::http::header;
+use actix_web::{delete, get, web, HttpRequest, HttpResponse};
+use chrono::{Local, NaiveDateTime};
+use entity::prelude::*;
+use entity::review::{Userextra, ERASED_REVIEWER_ID};
+use entity::user_achievement::GetAchievement;
+use entity::{erasure_audit, review, user_achievement};
+use sea_orm::ActiveValue::{NotSet, Set};
+use sea_orm::prelude::Json;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// 用户自己的评论。不包含其他用户的投票信息，只包含票数
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct PersonalReview {
+    pub id: i32,
+    pub course_id: Option<i32>,
+    pub title: String,
+    pub content: String,
+    /// 完整的修改历史
+    pub history: Json,
+    pub rank: Json,
+    pub time_created: NaiveDateTime,
+    pub time_updated: NaiveDateTime,
+    pub upvotes: usize,
+    pub downvotes: usize,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct PersonalVote {
+    pub review_id: i32,
+    pub course_id: Option<i32>,
+    /// 1 为赞同，-1 为反对
+    pub vote: i32,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct PersonalDataExport {
+    pub user_id: i32,
+    pub exported_at: NaiveDateTime,
+    pub reviews: Vec<PersonalReview>,
+    pub votes: Vec<PersonalVote>,
+    pub achievements: Vec<GetAchievement>,
+}
+
+fn voters_contain(voters: &Json, user_id: i32) -> bool {
+    voters
+        .as_array()
+        .is_some_and(|voters| voters.iter().any(|voter| voter.as_i64() == Some(user_id as i64)))
+}
+
+/// 从投票列表中移除用户，返回是否移除了
+fn remove_voter(voters: &mut Json, user_id: i32) -> bool {
+    let Some(voters) = voters.as_array_mut() else {
+        return false;
+    };
+    let len = voters.len();
+    voters.retain(|voter| voter.as_i64() != Some(user_id as i64));
+    voters.len() != len
+}
+
+/// 将历史记录中的修改者 `alter_by` 和原评论者 `original.reviewer_id` 替换为 [`ERASED_REVIEWER_ID`]，返回是否有替换
+fn anonymize_history(history: &mut Json, user_id: i32) -> bool {
+    let Some(entries) = history.as_array_mut() else {
+        return false;
+    };
+    let mut changed = false;
+    for entry in entries {
+        for pointer in ["/alter_by", "/original/reviewer_id"] {
+            if let Some(id) = entry.pointer_mut(pointer) {
+                if id.as_i64() == Some(user_id as i64) {
+                    *id = ERASED_REVIEWER_ID.into();
+                    changed = true;
+                }
+            }
+        }
+    }
+    changed
+}
+
+/// 投票和历史记录保存在评论的 JSON 列中，无法跨数据库按用户查询，只能分页遍历
+const REVIEW_PAGE_SIZE: u64 = 500;
+
+/// 按 id 分页读取 `after` 之后的评论
+async fn review_page<C: ConnectionTrait>(
+    after: i32,
+    db: &C,
+) -> Result<Vec<review::Model>, DbErr> {
+    Review::find()
+        .filter(review::Column::Id.gt(after))
+        .order_by_asc(review::Column::Id)
+        .limit(REVIEW_PAGE_SIZE)
+        .all(db)
+        .await
+}
+
+async fn export_personal_data(
+    user_id: i32,
+    db: &DatabaseConnection,
+) -> Result<PersonalDataExport, DbErr> {
+    let count = |voters: &Json| voters.as_array().map_or(0, |v| v.len());
+    let reviews = Review::find()
+        .filter(review::Column::ReviewerId.eq(user_id))
+        .order_by_asc(review::Column::Id)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|review| PersonalReview {
+            id: review.id,
+            course_id: review.course_id,
+            upvotes: count(&review.upvoters),
+            downvotes: count(&review.downvoters),
+            title: review.title,
+            content: review.content,
+            history: review.history,
+            rank: review.rank,
+            time_created: review.time_created,
+            time_updated: review.time_updated,
+        })
+        .collect();
+
+    let mut votes = vec![];
+    let mut after = 0;
+    loop {
+        let page = review_page(after, db).await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        after = last.id;
+        for review in page {
+            let vote = if voters_contain(&review.upvoters, user_id) {
+                1
+            } else if voters_contain(&review.downvoters, user_id) {
+                -1
+            } else {
+                continue;
+            };
+            votes.push(PersonalVote {
+                review_id: review.id,
+                course_id: review.course_id,
+                vote,
+            });
+        }
+    }
+
+    Ok(PersonalDataExport {
+        user_id,
+        exported_at: Local::now().naive_utc(),
+        reviews,
+        votes,
+        achievements: GetAchievement::load(user_id, db).await?,
+    })
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Everything stored about the current user, as a JSON archive.", body = PersonalDataExport),
+(status = 429, description = "Too many requests in a short time.", body = ErrorMessage,
+example = json ! (ApiError::RateLimited { retry_after: 600 }.to_message())),
+),
+security(("auth" = []))
+)]
+#[get("/users/me/export")]
+pub async fn export_my_data(
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    let export = export_personal_data(user_info.id, db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to export personal data."))?;
+    Ok(HttpResponse::Ok()
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"user-{}.json\"", user_info.id),
+        ))
+        .json(export))
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ErasureMode {
+    /// 保留评论内容，但与用户解除关联
+    #[default]
+    Anonymize,
+    /// 删除评论
+    Delete,
+}
+
+impl ErasureMode {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ErasureMode::Anonymize => "anonymize",
+            ErasureMode::Delete => "delete",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ErasureOptions {
+    /// `anonymize` (default) keeps the reviews but unlinks them from the user; `delete` removes them.
+    #[serde(default)]
+    #[param(value_type = Option<String>)]
+    pub mode: ErasureMode,
+}
+
+/// 擦除用户数据：匿名化或删除其评论，移除其投票，清空其成就，并留下审计记录。
+async fn erase_personal_data(
+    user_id: i32,
+    requested_by: i32,
+    mode: ErasureMode,
+    db: &DatabaseConnection,
+) -> Result<erasure_audit::Model, DbErr> {
+    let transaction = db.begin().await?;
+    let mut reviews_affected = 0;
+    if mode == ErasureMode::Delete {
+        reviews_affected = Review::delete_many()
+            .filter(review::Column::ReviewerId.eq(user_id))
+            .exec(&transaction)
+            .await?
+            .rows_affected as i32;
+    }
+    let mut votes_removed = 0;
+    let mut after = 0;
+    loop {
+        let page = review_page(after, &transaction).await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        after = last.id;
+        for review in page {
+            let is_own = review.reviewer_id == user_id;
+            let mut upvoters = review.upvoters.clone();
+            let mut downvoters = review.downvoters.clone();
+            let mut history = review.history.clone();
+            let mut removed = 0;
+            if remove_voter(&mut upvoters, user_id) {
+                removed += 1;
+            }
+            if remove_voter(&mut downvoters, user_id) {
+                removed += 1;
+            }
+            // 管理员可能修改过他人的评论，其 id 也会出现在他人评论的历史记录中
+            let history_changed = anonymize_history(&mut history, user_id);
+            if removed == 0 && !history_changed && !is_own {
+                continue;
+            }
+            votes_removed += removed;
+            let mut updated_review: review::ActiveModel = review.into();
+            updated_review.upvoters = Set(upvoters);
+            updated_review.downvoters = Set(downvoters);
+            updated_review.history = Set(history);
+            if is_own {
+                updated_review.reviewer_id = Set(ERASED_REVIEWER_ID);
+                reviews_affected += 1;
+            }
+            updated_review.update(&transaction).await?;
+        }
+    }
+    let achievements_removed = UserAchievement::delete_many()
+        .filter(user_achievement::Column::UserId.eq(user_id))
+        .exec(&transaction)
+        .await?
+        .rows_affected as i32;
+
+    let audit = erasure_audit::ActiveModel {
+        id: NotSet,
+        user_id: Set(user_id),
+        requested_by: Set(requested_by),
+        mode: Set(mode.as_str().to_string()),
+        reviews_affected: Set(reviews_affected),
+        votes_removed: Set(votes_removed),
+        achievements_removed: Set(achievements_removed),
+        time_created: Set(Local::now().naive_utc()),
+    }
+        .insert(&transaction)
+        .await?;
+    transaction.commit().await?;
+
+    Userextra::invalidate(user_id).await;
+    Ok(audit)
+}
+
+async fn erase(
+    user_id: i32,
+    user_info: &UserInfo,
+    mode: ErasureMode,
+    db: &DatabaseConnection,
+) -> actix_web::Result<HttpResponse> {
+    if user_id != user_info.id && !user_info.is_admin {
+        return Err(ApiError::NotDataOwner { user_id }.into());
+    }
+    if user_id == ERASED_REVIEWER_ID {
+        return Err(ApiError::InvalidUserId { user_id }.into());
+    }
+    let audit = erase_personal_data(user_id, user_info.id, mode, db)
+        .await
+        .map_err(ApiError::db(format!("Unable to erase the data of user {}.", user_id)))?;
+    Ok(HttpResponse::Ok().json(audit))
+}
+
+#[utoipa::path(
+params(ErasureOptions),
+responses(
+(status = 200, description = "The data of the current user is erased. Returns the audit record.", body = erasure_audit::Model),
+(status = 429, description = "Too many requests in a short time.", body = ErrorMessage,
+example = json ! (ApiError::RateLimited { retry_after: 600 }.to_message())),
+),
+security(("auth" = []))
+)]
+#[delete("/users/me/data")]
+pub async fn erase_my_data(
+    options: web::Query<ErasureOptions>,
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    erase(user_info.id, &user_info, options.mode, db.get_ref()).await
+}
+
+#[utoipa::path(
+params(("user_id" = i32, Path, description = "Id of the user."), ErasureOptions),
+responses(
+(status = 200, description = "The data of the user is erased. Returns the audit record.", body = erasure_audit::Model),
+(status = 400, description = "Invalid user id.", body = ErrorMessage),
+(status = 403, description = "Only admin can erase the data of other users.", body = ErrorMessage),
+(status = 429, description = "Too many requests in a short time.", body = ErrorMessage,
+example = json ! (ApiError::RateLimited { retry_after: 600 }.to_message())),
+),
+security(("auth" = []))
+)]
+#[delete("/users/{user_id}/data")]
+pub async fn erase_user_data(
+    options: web::Query<ErasureOptions>,
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    let user_id = path_id(&req, "user_id")?;
+    erase(user_id, &user_info, options.mode, db.get_ref()).await
+}
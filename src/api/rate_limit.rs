@@ -0,0 +1,164 @@
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::web;
+use if_chain::if_chain;
+use moka::future::{Cache, CacheBuilder};
+use crate::api::auth::require_authentication;
+use crate::api::error_handler::ApiError;
+use crate::settings::RateLimitSettings;
+use serde::Deserialize;
+
+/// 在 `period` 时间内最多允许 `max_requests` 次请求。
+///
+/// 配置字符串的格式为 `次数/秒数`，例如 `10/60` 表示每分钟最多 10 次。
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct RateLimit {
+    pub max_requests: u32,
+    pub period: Duration,
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid rate limit `{}`, expected `<max requests>/<seconds>`.", s);
+        let (max_requests, seconds) = s.trim().split_once('/').ok_or_else(invalid)?;
+        let max_requests = max_requests.trim().parse::<u32>().map_err(|_| invalid())?;
+        let seconds = seconds.trim().parse::<u64>().map_err(|_| invalid())?;
+        if max_requests == 0 || seconds == 0 {
+            return Err(invalid());
+        }
+        Ok(RateLimit {
+            max_requests,
+            period: Duration::from_secs(seconds),
+        })
+    }
+}
+
+impl TryFrom<String> for RateLimit {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitRule {
+    pub name: &'static str,
+    pub method: Method,
+    pub pattern: &'static str,
+    pub limit: RateLimit,
+}
+
+// 固定窗口计数器
+struct Window {
+    start: Instant,
+    count: u32,
+}
+
+pub struct RateLimitConfig {
+    rules: Vec<RateLimitRule>,
+    counters: Cache<(&'static str, i32), Arc<Mutex<Window>>>,
+}
+
+impl RateLimitConfig {
+    pub fn new(rules: Vec<RateLimitRule>) -> Self {
+        // 计数器最多存活一个最长的窗口周期，过期后自然被 moka 清理
+        let longest_period = rules
+            .iter()
+            .map(|rule| rule.limit.period)
+            .max()
+            .unwrap_or(Duration::from_secs(60));
+        RateLimitConfig {
+            rules,
+            counters: CacheBuilder::new(100000)
+                .time_to_live(longest_period)
+                .build(),
+        }
+    }
+
+    /// 按配置限制各个写操作。同名的规则共用一个计数器。
+    pub fn from_settings(settings: &RateLimitSettings) -> Self {
+        let rules = [
+            ("add_review", Method::POST, "/courses/{course_id}/reviews", settings.add_review),
+            ("modify_review", Method::PUT, "/reviews/{review_id}", settings.modify_review),
+            ("vote_for_review", Method::PATCH, "/reviews/{review_id}", settings.vote_for_review),
+            ("export_personal_data", Method::GET, "/users/me/export", settings.export_personal_data),
+            ("erase_personal_data", Method::DELETE, "/users/me/data", settings.erase_personal_data),
+            ("erase_personal_data", Method::DELETE, "/users/{user_id}/data", settings.erase_personal_data),
+        ]
+            .into_iter()
+            .map(|(name, method, pattern, limit)| RateLimitRule {
+                name,
+                method,
+                pattern,
+                limit,
+            })
+            .collect();
+        RateLimitConfig::new(rules)
+    }
+
+    fn find_rule(&self, method: &Method, pattern: &str) -> Option<&RateLimitRule> {
+        self.rules
+            .iter()
+            .find(|rule| rule.method == *method && rule.pattern == pattern)
+    }
+
+    /// 记录一次请求。超出限制时返回距离窗口重置还需等待的秒数。
+    pub async fn hit(&self, rule: &RateLimitRule, user_id: i32) -> Result<(), u64> {
+        let window = self
+            .counters
+            .get_with((rule.name, user_id), async {
+                Arc::new(Mutex::new(Window {
+                    start: Instant::now(),
+                    count: 0,
+                }))
+            })
+            .await;
+        let mut window = window.lock().unwrap();
+        let elapsed = window.start.elapsed();
+        if elapsed >= rule.limit.period {
+            window.start = Instant::now();
+            window.count = 0;
+        }
+        if window.count >= rule.limit.max_requests {
+            let remaining = rule.limit.period.saturating_sub(window.start.elapsed());
+            // 向上取整，避免客户端过早重试
+            return Err(remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0));
+        }
+        window.count += 1;
+        Ok(())
+    }
+}
+
+/// 按用户、按路由限制写操作和开销较大的操作的频率。管理员不受限制。
+pub async fn rate_limit(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let config = req.app_data::<web::Data<RateLimitConfig>>().cloned();
+    if_chain! {
+        if let Some(config) = config;
+        if let Some(pattern) = req.match_pattern();
+        if let Some(rule) = config.find_rule(req.method(), &pattern);
+        // 未通过身份验证的请求交给处理函数返回 401
+        if let Ok(user_info) = require_authentication(req.request()).await;
+        if !user_info.is_admin;
+        then {
+            if let Err(retry_after) = config.hit(rule, user_info.id).await {
+                // 直接返回响应而非错误，使外层的日志中间件能添加请求 id
+                return Ok(req
+                    .error_response(ApiError::RateLimited { retry_after })
+                    .map_into_right_body());
+            }
+        }
+    }
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
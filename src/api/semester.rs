@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+use crate::api::auth::require_authentication;
+use crate::api::curriculum_board::invalidate_course_group_cache;
+use crate::api::error_handler::{is_unique_violation, path_id, ApiError};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use chrono::NaiveDate;
+use entity::prelude::*;
+use entity::semester::{GetSemester, NewSemester};
+use entity::{course, semester};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait,
+    FromQueryResult, ModelTrait, QueryFilter, QuerySelect, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 已登记或有课程的学期。未登记的学期只有学年、编号和课程数
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, ToSchema)]
+pub struct SemesterSummary {
+    pub year: i32,
+    pub semester: i32,
+    pub id: Option<i32>,
+    pub name: Option<String>,
+    #[schema(value_type = Option<String>, format = Date)]
+    pub start_date: Option<NaiveDate>,
+    #[schema(value_type = Option<String>, format = Date)]
+    pub end_date: Option<NaiveDate>,
+    pub is_current: bool,
+    /// 该学期的课程数
+    pub course_count: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct CourseCount {
+    year: i32,
+    semester: i32,
+    course_count: i64,
+}
+
+/// 已登记的学期以及课程所在的学期，从新到旧排列
+pub async fn list_semesters(db: &DatabaseConnection) -> Result<Vec<SemesterSummary>, DbErr> {
+    let mut summaries: BTreeMap<(i32, i32), SemesterSummary> = BTreeMap::new();
+    for semester in Semester::find().all(db).await? {
+        summaries.insert(
+            (semester.year, semester.semester),
+            SemesterSummary {
+                year: semester.year,
+                semester: semester.semester,
+                id: Some(semester.id),
+                name: semester.name,
+                start_date: semester.start_date,
+                end_date: semester.end_date,
+                is_current: semester.is_current,
+                course_count: 0,
+            },
+        );
+    }
+    let counts = Course::find()
+        .select_only()
+        .column(course::Column::Year)
+        .column(course::Column::Semester)
+        .column_as(Expr::col(course::Column::Id).count(), "course_count")
+        .group_by(course::Column::Year)
+        .group_by(course::Column::Semester)
+        .into_model::<CourseCount>()
+        .all(db)
+        .await?;
+    for count in counts {
+        summaries
+            .entry((count.year, count.semester))
+            .or_insert_with(|| SemesterSummary {
+                year: count.year,
+                semester: count.semester,
+                id: None,
+                name: None,
+                start_date: None,
+                end_date: None,
+                is_current: false,
+                course_count: 0,
+            })
+            .course_count = count.course_count;
+    }
+    Ok(summaries.into_values().rev().collect())
+}
+
+async fn find_semester(semester_id: i32, db: &DatabaseConnection) -> Result<semester::Model, ApiError> {
+    Semester::find_by_id(semester_id)
+        .one(db)
+        .await
+        .map_err(ApiError::db("Unable to fetch the semester."))?
+        .ok_or(ApiError::SemesterNotFound { id: semester_id })
+}
+
+/// 检查日期，以及同一学年中是否已有相同编号的其他学期
+async fn validate_semester(
+    new_semester: &NewSemester,
+    semester_id: Option<i32>,
+    db: &DatabaseConnection,
+) -> Result<(), ApiError> {
+    if let (Some(start_date), Some(end_date)) = (new_semester.start_date, new_semester.end_date) {
+        if end_date < start_date {
+            return Err(ApiError::InvalidRequest {
+                reason: "`end_date` cannot be earlier than `start_date`.".to_string(),
+            });
+        }
+    }
+    let existing = Semester::find()
+        .filter(semester::Column::Year.eq(new_semester.year))
+        .filter(semester::Column::Semester.eq(new_semester.semester))
+        .one(db)
+        .await
+        .map_err(ApiError::db("Unable to fetch the semester."))?;
+    match existing {
+        Some(existing) if Some(existing.id) != semester_id => Err(ApiError::DuplicateSemester {
+            year: new_semester.year,
+            semester: new_semester.semester,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// 插入或修改学期时，与其他请求同时登记了相同的学期返回 409
+fn semester_error(year: i32, semester: i32, context: &'static str) -> impl FnOnce(DbErr) -> ApiError {
+    move |e| {
+        if is_unique_violation(&e) {
+            ApiError::DuplicateSemester { year, semester }
+        } else {
+            ApiError::db(context)(e)
+        }
+    }
+}
+
+/// 设置新的当前学期前，取消其他学期的当前学期标记
+async fn clear_current_semester(transaction: &DatabaseTransaction) -> Result<(), DbErr> {
+    Semester::update_many()
+        .col_expr(semester::Column::IsCurrent, Expr::value(false))
+        .filter(semester::Column::IsCurrent.eq(true))
+        .exec(transaction)
+        .await?;
+    Ok(())
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Registered semesters and semesters that have courses, from the latest to the earliest. \
+Semesters that have courses but are not registered have no `id`, `name` or dates.", body = [SemesterSummary]),
+)
+)]
+#[get("/semesters")]
+pub async fn get_semesters(db: web::Data<DatabaseConnection>) -> actix_web::Result<HttpResponse> {
+    let semesters = list_semesters(db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to list the semesters."))?;
+    Ok(HttpResponse::Ok().json(semesters))
+}
+
+#[utoipa::path(
+request_body = NewSemester,
+responses(
+(status = 200, description = "Semester created successfully.", body = GetSemester),
+(status = 403, description = "Only admin can manage semesters.", body = ErrorMessage,
+example = json ! (ApiError::AdminRequired.to_message())),
+(status = 409, description = "The year already has a semester with the same number.", body = ErrorMessage,
+example = json ! (ApiError::DuplicateSemester { year: 2022, semester: 1 }.to_message())),
+),
+security(("auth" = []))
+)]
+#[post("/semesters")]
+pub async fn add_semester(
+    new_semester: web::Json<NewSemester>,
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    user_info.require_admin()?;
+    let new_semester = new_semester.into_inner();
+    validate_semester(&new_semester, None, db.get_ref()).await?;
+
+    let add_error = "Unable to create new semester.";
+    let transaction = db.begin().await.map_err(ApiError::db(add_error))?;
+    if new_semester.is_current {
+        clear_current_semester(&transaction).await.map_err(ApiError::db(add_error))?;
+    }
+    let (year, number) = (new_semester.year, new_semester.semester);
+    let semester_added = new_semester
+        .into_active_model()
+        .insert(&transaction)
+        .await
+        .map_err(semester_error(year, number, add_error))?;
+    transaction.commit().await.map_err(ApiError::db(add_error))?;
+    // 课程组列表中包含学期名称
+    invalidate_course_group_cache();
+    Ok(HttpResponse::Ok().json(GetSemester::from(semester_added)))
+}
+
+#[utoipa::path(
+params(("semester_id" = i32, Path, description = "Id of the semester.")),
+request_body = NewSemester,
+responses(
+(status = 200, description = "Semester modified successfully.", body = GetSemester),
+(status = 403, description = "Only admin can manage semesters.", body = ErrorMessage,
+example = json ! (ApiError::AdminRequired.to_message())),
+(status = 404, description = "Semester with given id not found.", body = ErrorMessage,
+example = json ! (ApiError::SemesterNotFound { id: 1 }.to_message())),
+(status = 409, description = "The year already has another semester with the same number.", body = ErrorMessage,
+example = json ! (ApiError::DuplicateSemester { year: 2022, semester: 1 }.to_message())),
+),
+security(("auth" = []))
+)]
+#[put("/semesters/{semester_id}")]
+pub async fn modify_semester(
+    new_semester: web::Json<NewSemester>,
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    user_info.require_admin()?;
+    let semester_id = path_id(&req, "semester_id")?;
+    let semester = find_semester(semester_id, db.get_ref()).await?;
+    let new_semester = new_semester.into_inner();
+    validate_semester(&new_semester, Some(semester_id), db.get_ref()).await?;
+
+    let update_error = "Unable to update the semester.";
+    let transaction = db.begin().await.map_err(ApiError::db(update_error))?;
+    if new_semester.is_current {
+        clear_current_semester(&transaction).await.map_err(ApiError::db(update_error))?;
+    }
+    let (year, number) = (new_semester.year, new_semester.semester);
+    let mut updated_semester: semester::ActiveModel = semester.into();
+    updated_semester.update_with(new_semester);
+    let updated_semester = updated_semester
+        .update(&transaction)
+        .await
+        .map_err(semester_error(year, number, update_error))?;
+    transaction.commit().await.map_err(ApiError::db(update_error))?;
+    invalidate_course_group_cache();
+    Ok(HttpResponse::Ok().json(GetSemester::from(updated_semester)))
+}
+
+#[utoipa::path(
+params(("semester_id" = i32, Path, description = "Id of the semester.")),
+responses(
+(status = 200, description = "Semester deleted successfully. Its courses are kept, without a semester name.", body = GetSemester),
+(status = 403, description = "Only admin can manage semesters.", body = ErrorMessage,
+example = json ! (ApiError::AdminRequired.to_message())),
+(status = 404, description = "Semester with given id not found.", body = ErrorMessage,
+example = json ! (ApiError::SemesterNotFound { id: 1 }.to_message())),
+),
+security(("auth" = []))
+)]
+#[delete("/semesters/{semester_id}")]
+pub async fn delete_semester(
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    user_info.require_admin()?;
+    let semester_id = path_id(&req, "semester_id")?;
+    let semester = find_semester(semester_id, db.get_ref()).await?;
+    semester
+        .clone()
+        .delete(db.get_ref())
+        .await
+        .map_err(ApiError::db("Unable to delete the semester."))?;
+    invalidate_course_group_cache();
+    Ok(HttpResponse::Ok().json(GetSemester::from(semester)))
+}
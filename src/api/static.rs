@@ -1,12 +1,22 @@
-use actix_web::{HttpRequest, Result, get};
+use std::io;
+use std::path::Path;
+use actix_web::{web, HttpRequest, Result, get};
 use actix_files::NamedFile;
+use crate::api::error_handler::ApiError;
+use crate::settings::Settings;
 
 #[utoipa::path(
 responses(
 (status = 200, description = "Get the static dict file for cedict.", content_type = "text/plain"),
+(status = 404, description = "The dict file is missing from the static directory.", body = ErrorMessage),
 )
 )]
 #[get("/static/cedict_ts.u8")]
-pub async fn cedict(_unused: HttpRequest) -> Result<NamedFile> {
-    Ok(NamedFile::open("static/cedict_ts.u8")?.set_content_type(mime::TEXT_PLAIN_UTF_8).disable_content_disposition())
-}
\ No newline at end of file
+pub async fn cedict(req: HttpRequest, settings: web::Data<Settings>) -> Result<NamedFile> {
+    let file = NamedFile::open(Path::new(&settings.server.static_dir).join("cedict_ts.u8"))
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => ApiError::RouteNotFound { path: req.path().to_string() },
+            _ => ApiError::internal(format!("Unable to open the cedict file. Error: {}", e)),
+        })?;
+    Ok(file.set_content_type(mime::TEXT_PLAIN_UTF_8).disable_content_disposition())
+}
@@ -0,0 +1,92 @@
+use crate::achievement_rule::UserStats;
+use crate::api::auth::{require_authentication, UserInfo};
+use crate::api::error_handler::{path_id, ApiError};
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use entity::review::Userextra;
+use entity::user_achievement::GetAchievement;
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Deserialize, Serialize, ToSchema)]
+pub struct UserProfile {
+    pub user_id: i32,
+    pub achievements: Vec<GetAchievement>,
+    /// 评论数。与以下统计信息一样，仅本人和管理员可见，以免通过统计信息关联评论者的假名
+    pub review_count: Option<usize>,
+    /// 所有评论获得的赞同数之和
+    pub total_upvotes: Option<i64>,
+    /// 评论过的课程所属院系
+    pub departments: Option<Vec<String>>,
+}
+
+async fn load_profile(
+    user_id: i32,
+    viewer: &UserInfo,
+    db: &DatabaseConnection,
+) -> actix_web::Result<HttpResponse> {
+    let context = format!("Unable to load the profile of user {}.", user_id);
+    let extra = Userextra::load(user_id, db).await.map_err(ApiError::db(&context))?;
+    let stats = UserStats::load(user_id, db).await.map_err(ApiError::db(context))?;
+    // 没有评论和成就的其他用户无从区分是否存在，一律视为不存在
+    if viewer.id != user_id && stats.review_count == 0 && extra.achievements.is_empty() {
+        return Err(ApiError::UserNotFound { user_id }.into());
+    }
+
+    // 其他用户只能看到成就
+    let profile = if viewer.id == user_id || viewer.is_admin {
+        let mut departments: Vec<String> = stats.departments.into_iter().collect();
+        departments.sort();
+        UserProfile {
+            user_id,
+            achievements: extra.achievements,
+            review_count: Some(stats.review_count),
+            total_upvotes: Some(stats.total_upvotes),
+            departments: Some(departments),
+        }
+    } else {
+        UserProfile {
+            user_id,
+            achievements: extra.achievements,
+            review_count: None,
+            total_upvotes: None,
+            departments: None,
+        }
+    };
+    Ok(HttpResponse::Ok().json(profile))
+}
+
+#[utoipa::path(
+responses(
+(status = 200, description = "Profile of the current user, including private fields.", body = UserProfile),
+),
+security(("auth" = []))
+)]
+#[get("/users/me/profile")]
+pub async fn get_my_profile(
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    load_profile(user_info.id, &user_info, db.get_ref()).await
+}
+
+#[utoipa::path(
+params(("user_id" = i32, Path, description = "Id of the user.")),
+responses(
+(status = 200, description = "Profile of the user. The statistics are only returned to the user themselves and admins.", body = UserProfile),
+(status = 400, description = "Invalid user id.", body = ErrorMessage),
+(status = 404, description = "The user has no reviews or achievements.", body = ErrorMessage,
+example = json ! (ApiError::UserNotFound { user_id: 1 }.to_message())),
+),
+security(("auth" = []))
+)]
+#[get("/users/{user_id}/profile")]
+pub async fn get_user_profile(
+    req: HttpRequest,
+    db: web::Data<DatabaseConnection>,
+) -> actix_web::Result<HttpResponse> {
+    let user_info = require_authentication(&req).await?;
+    let user_id = path_id(&req, "user_id")?;
+    load_profile(user_id, &user_info, db.get_ref()).await
+}
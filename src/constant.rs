@@ -1,2 +1,29 @@
+pub const ENV_CONFIG_FILE: &str = "CONFIG_FILE";
+pub const ENV_ENVIRONMENT: &str = "APP_ENV";
+pub const ENV_BIND_ADDRESS: &str = "BIND_ADDRESS";
+pub const ENV_METRICS_BIND_ADDRESS: &str = "METRICS_BIND_ADDRESS";
+pub const ENV_WORKERS: &str = "WORKERS";
+pub const ENV_SHUTDOWN_TIMEOUT: &str = "SHUTDOWN_TIMEOUT";
+pub const ENV_STATIC_DIR: &str = "STATIC_DIR";
 pub const ENV_DB_URL: &str = "DB_URL";
-pub const ENV_USER_VERIFICATION_ADDRESS: &str = "AUTH_API_URL";
\ No newline at end of file
+pub const ENV_DB_MAX_CONNECTIONS: &str = "DB_MAX_CONNECTIONS";
+pub const ENV_DB_MIN_CONNECTIONS: &str = "DB_MIN_CONNECTIONS";
+pub const ENV_AUTO_MIGRATE: &str = "AUTO_MIGRATE";
+pub const ENV_AUTH_MODE: &str = "AUTH_MODE";
+pub const ENV_DEV_USER_ID: &str = "DEV_USER_ID";
+pub const ENV_USER_VERIFICATION_ADDRESS: &str = "AUTH_API_URL";
+pub const ENV_AUTH_READINESS_CHECK: &str = "AUTH_READINESS_CHECK";
+pub const ENV_USER_CACHE_CAPACITY: &str = "USER_CACHE_CAPACITY";
+pub const ENV_USER_CACHE_TTL: &str = "USER_CACHE_TTL";
+pub const ENV_USER_EXTRA_CACHE_CAPACITY: &str = "USER_EXTRA_CACHE_CAPACITY";
+pub const ENV_USER_EXTRA_CACHE_TTL: &str = "USER_EXTRA_CACHE_TTL";
+pub const ENV_RATE_LIMIT_ADD_REVIEW: &str = "RATE_LIMIT_ADD_REVIEW";
+pub const ENV_RATE_LIMIT_MODIFY_REVIEW: &str = "RATE_LIMIT_MODIFY_REVIEW";
+pub const ENV_RATE_LIMIT_VOTE_FOR_REVIEW: &str = "RATE_LIMIT_VOTE_FOR_REVIEW";
+pub const ENV_RATE_LIMIT_EXPORT_PERSONAL_DATA: &str = "RATE_LIMIT_EXPORT_PERSONAL_DATA";
+pub const ENV_RATE_LIMIT_ERASE_PERSONAL_DATA: &str = "RATE_LIMIT_ERASE_PERSONAL_DATA";
+pub const ENV_ACHIEVEMENT_RULES_FILE: &str = "ACHIEVEMENT_RULES_FILE";
+pub const ENV_PSEUDONYM_SECRET: &str = "PSEUDONYM_SECRET";
+pub const ENV_LOG_LEVEL: &str = "LOG_LEVEL";
+pub const ENV_DOCS_ENABLED: &str = "DOCS_ENABLED";
+pub const ENV_DOCS_SERVERS: &str = "DOCS_SERVERS";
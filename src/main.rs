@@ -1,25 +1,108 @@
+mod achievement_rule;
 mod api;
 mod constant;
+mod pseudonym;
+mod settings;
 mod tests;
 
-use std::env;
+use std::io;
+use achievement_rule::AchievementRules;
+use api::achievement;
+use api::logging::log_request;
+use api::metrics;
+use api::auth;
 use api::curriculum_board;
+use api::error_handler;
+use api::export;
+use api::health;
+use api::i18n::{negotiate_locale, Locale};
+use api::personal_data;
 use api::r#static;
+use api::semester;
+use api::user;
+use entity::review::Userextra;
+use pseudonym::Pseudonymizer;
+use settings::{Environment, Settings};
+use api::rate_limit::{rate_limit, RateLimitConfig};
 use actix_web::{web, App, HttpServer, middleware};
 use dotenv::dotenv;
-use sea_orm::{Database, DatabaseConnection};
+use tracing_subscriber::EnvFilter;
+use sea_orm::{ConnectOptions, Database, DatabaseConnection};
 use migration::{Migrator, MigratorTrait};
 
 
+/// 全部接口，按注册顺序排列。`config` 和 OpenAPI 文档都由这张表生成，新增接口时只需加在这里。
+///
+/// 用法为 `api_services!(callback)`，展开为 `callback! { module::handler, ... }`
+macro_rules! api_services {
+    ($callback:ident) => {
+        $callback! {
+            curriculum_board::hello,
+            curriculum_board::get_course_groups_hash,
+            curriculum_board::refresh_course_groups_cache,
+            curriculum_board::get_course_groups,
+            semester::get_semesters,
+            semester::add_semester,
+            semester::modify_semester,
+            semester::delete_semester,
+            curriculum_board::get_course_group,
+            curriculum_board::add_course,
+            curriculum_board::merge_course_groups,
+            curriculum_board::get_course,
+            curriculum_board::add_review,
+            curriculum_board::modify_review,
+            curriculum_board::vote_for_review,
+            curriculum_board::get_reviews,
+            curriculum_board::get_random_reviews,
+            export::export_courses,
+            export::export_groups,
+            export::export_reviews,
+            achievement::get_achievements,
+            achievement::add_achievement,
+            achievement::modify_achievement,
+            achievement::delete_achievement,
+            achievement::grant_achievement,
+            achievement::revoke_achievement,
+            achievement::backfill_achievements,
+            // `/users/me/profile` 须在 `/users/{user_id}/profile` 之前注册
+            user::get_my_profile,
+            user::get_user_profile,
+            // `/users/me/data` 须在 `/users/{user_id}/data` 之前注册
+            personal_data::export_my_data,
+            personal_data::erase_my_data,
+            personal_data::erase_user_data,
+            r#static::cedict,
+            health::liveness,
+            health::readiness,
+        }
+    };
+}
+
 mod openapi {
-    use actix_web::get;
+    use actix_web::{get, web, HttpRequest, HttpResponse};
+    use actix_web::http::header::{self, ContentType};
     use utoipa::{Modify, OpenApi};
+    use utoipa::openapi::{ContentBuilder, Ref, Response, ResponseBuilder};
+    use utoipa::openapi::schema::RefOr;
     use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+    use utoipa::openapi::server::Server;
     use crate::{
+        achievement,
+        api::error_handler::{self, ApiError},
+        achievement_rule,
         curriculum_board,
+        export,
+        health,
+        personal_data,
         r#static,
+        semester,
+        settings::Settings,
+        user,
     };
-    use entity::course::{GetSingleCourse, NewCourse};
+    use entity::achievement::{GetAchievementInfo, NewAchievement};
+    use entity::erasure_audit;
+    use entity::semester::{GetSemester, NewSemester};
+    use entity::course::{GetMultiCourse, GetSingleCourse, NewCourse};
     use entity::coursegroup::{GetMultiCourseGroup, GetSingleCourseGroup, NewCourseGroup};
     use entity::review::{GetMyReview, GetReview, HistoryReview, NewReview, Userextra};
     use entity::user_achievement::GetAchievement;
@@ -36,61 +119,203 @@ mod openapi {
         }
     }
 
-    #[derive(OpenApi)]
-    #[openapi(paths(curriculum_board::hello,
-    curriculum_board::get_course_groups_hash,
-    curriculum_board::refresh_course_groups_cache,
-    curriculum_board::get_course_groups,
-    curriculum_board::get_course_group,
-    curriculum_board::add_course,
-    curriculum_board::get_course,
-    curriculum_board::add_review,
-    curriculum_board::modify_review,
-    curriculum_board::vote_for_review,
-    curriculum_board::get_reviews,
-    curriculum_board::get_random_reviews,
-    r#static::cedict
-    ),
-    components(schemas(
-    GetMultiCourseGroup,
-    GetSingleCourseGroup,
-    NewCourseGroup,
-    GetSingleCourse,
-    GetMyReview,
-    GetReview,
-    Userextra,
-    HistoryReview,
-    NewReview,
-    NewCourse,
-    GetAchievement,
-    curriculum_board::HashMessage,
-    curriculum_board::NewVote)),
-    modifiers(& AuthorizationAddon))]
-    pub(crate) struct ApiDoc;
+    /// 不会返回 500 的接口，不补充 500 响应。`/readyz` 未就绪时返回的 503 已在接口上单独说明
+    const INFALLIBLE_PATHS: [&str; 7] = ["/", "/courses/refresh", "/healthz", "/readyz", "/openapi.json", "/docs", "/docs/{file}"];
+
+    fn error_response(description: &str, example: ApiError) -> RefOr<Response> {
+        let content = ContentBuilder::new()
+            .schema(Ref::from_schema_name("ErrorMessage"))
+            .example(serde_json::to_value(example.to_message()).ok())
+            .build();
+        ResponseBuilder::new()
+            .description(description)
+            .content("application/json", content)
+            .build()
+            .into()
+    }
+
+    /// 补充各接口共有的错误响应，已经单独说明的不会被覆盖：有参数或请求体的接口可能返回 400，
+    /// 需要登录的接口可能返回 401，除 [`INFALLIBLE_PATHS`] 外的接口都可能返回 500
+    struct ErrorResponsesAddon;
+
+    impl Modify for ErrorResponsesAddon {
+        fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+            for (path, item) in openapi.paths.paths.iter_mut() {
+                for operation in item.operations.values_mut() {
+                    let has_input = operation.parameters.as_ref().is_some_and(|parameters| !parameters.is_empty())
+                        || operation.request_body.is_some();
+                    let requires_auth = operation.security.is_some();
+                    let responses = &mut operation.responses.responses;
+                    if has_input {
+                        responses.entry("400".to_string()).or_insert_with(|| {
+                            error_response(
+                                "Invalid path parameters, query parameters or request body.",
+                                ApiError::InvalidRequest { reason: "missing field `title`".to_string() },
+                            )
+                        });
+                    }
+                    if requires_auth {
+                        responses.entry("401".to_string()).or_insert_with(|| {
+                            error_response("Authorization information is missing or invalid.", ApiError::AuthenticationRequired)
+                        });
+                    }
+                    if !INFALLIBLE_PATHS.contains(&path.as_str()) {
+                        responses.entry("500".to_string()).or_insert_with(|| {
+                            error_response(
+                                "Internal server error, such as a database error.",
+                                ApiError::internal("Internal server error."),
+                            )
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// 文档中的接口为 `api_services!` 中的全部接口，加上文档本身
+    macro_rules! derive_api_doc {
+        ($($module:ident::$handler:ident),* $(,)?) => {
+            #[derive(OpenApi)]
+            #[openapi(paths($($module::$handler,)*
+            get_openapi,
+            docs,
+            docs_asset
+            ),
+            components(schemas(
+            GetMultiCourse,
+            GetMultiCourseGroup,
+            GetSingleCourseGroup,
+            NewCourseGroup,
+            GetSingleCourse,
+            GetMyReview,
+            GetReview,
+            Userextra,
+            HistoryReview,
+            NewReview,
+            NewCourse,
+            GetAchievement,
+            GetAchievementInfo,
+            NewAchievement,
+            achievement::GrantAchievement,
+            achievement_rule::BackfillResult,
+            user::UserProfile,
+            curriculum_board::HashMessage,
+            semester::SemesterSummary,
+            GetSemester,
+            NewSemester,
+            curriculum_board::MergeCourseGroups,
+            export::ExportFormat,
+            personal_data::PersonalDataExport,
+            personal_data::PersonalReview,
+            personal_data::PersonalVote,
+            personal_data::ErasureMode,
+            erasure_audit::Model,
+            health::HealthCheck,
+            health::Readiness,
+            curriculum_board::NewVote,
+            error_handler::ErrorMessage,
+            error_handler::ErrorCode)),
+            modifiers(& AuthorizationAddon, & ErrorResponsesAddon))]
+            pub(crate) struct ApiDoc;
+        };
+    }
+
+    api_services!(derive_api_doc);
+
+    /// 在生成的文档中加入构建信息，以及配置中的服务器地址
+    pub(crate) fn api_doc(settings: Option<&Settings>) -> utoipa::openapi::OpenApi {
+        let mut doc = ApiDoc::openapi();
+        let build_info = format!(
+            "Git commit: {}\n\nBuild time: {}\n\nRust compiler version: {}",
+            env!("VERGEN_GIT_SHA"),
+            env!("VERGEN_BUILD_TIMESTAMP"),
+            env!("VERGEN_RUSTC_SEMVER")
+        );
+        doc.info.description = Some(match doc.info.description.take().filter(|description| !description.is_empty()) {
+            Some(description) => format!("{}\n\n{}", description, build_info),
+            None => build_info,
+        });
+        if let Some(settings) = settings.filter(|settings| !settings.docs.servers.is_empty()) {
+            doc.servers = Some(settings.docs.servers.iter().map(Server::new).collect());
+        }
+        doc
+    }
+
+    /// 序列化后的 OpenAPI 文档。文档在运行期间不变，启动时生成一次；
+    /// 此时不在请求中，示例中的错误信息使用默认语言，也不含请求 id
+    pub(crate) struct ApiSpec(String);
 
+    impl ApiSpec {
+        pub(crate) fn new(settings: Option<&Settings>) -> Self {
+            ApiSpec(api_doc(settings).to_pretty_json().unwrap())
+        }
+    }
+
+    #[utoipa::path(
+    responses(
+    (status = 200, description = "This OpenAPI document", content_type = "application/json"),
+    )
+    )]
     #[get("/openapi.json")]
-    pub async fn get_openapi() -> actix_web::HttpResponse {
-        actix_web::HttpResponse::Ok()
+    pub async fn get_openapi(spec: web::Data<ApiSpec>) -> HttpResponse {
+        HttpResponse::Ok()
             .content_type("application/json")
-            .body(ApiDoc::openapi().to_pretty_json().unwrap())
+            .body(spec.0.clone())
+    }
+
+    /// Swagger UI，可以通过 `docs.enabled` 关闭
+    #[utoipa::path(
+    responses(
+    (status = 200, description = "Swagger UI for this OpenAPI document. Not served if disabled in the settings.", content_type = "text/html"),
+    )
+    )]
+    #[get("/docs")]
+    pub async fn docs() -> HttpResponse {
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(include_str!("docs.html"))
+    }
+
+    /// Swagger UI 的脚本和样式，取自 swagger-ui-dist 5.17.14，随程序一起发布，不依赖外部 CDN
+    const DOCS_ASSETS: [(&str, &str, &[u8]); 2] = [
+        ("swagger-ui.css", "text/css; charset=utf-8", include_bytes!("swagger-ui/swagger-ui.css")),
+        ("swagger-ui-bundle.js", "text/javascript; charset=utf-8", include_bytes!("swagger-ui/swagger-ui-bundle.js")),
+    ];
+
+    #[utoipa::path(
+    params(("file" = String, Path, description = "Name of the asset, `swagger-ui.css` or `swagger-ui-bundle.js`.")),
+    responses(
+    (status = 200, description = "A static asset of Swagger UI. Not served if disabled in the settings."),
+    (status = 404, description = "No such asset.", body = ErrorMessage),
+    )
+    )]
+    #[get("/docs/{file}")]
+    pub async fn docs_asset(file: web::Path<String>, req: HttpRequest) -> actix_web::Result<HttpResponse> {
+        let (_, content_type, content) = DOCS_ASSETS
+            .into_iter()
+            .find(|(name, _, _)| *name == file.as_str())
+            .ok_or_else(|| ApiError::RouteNotFound { path: req.path().to_string() })?;
+        Ok(HttpResponse::Ok()
+            .content_type(content_type)
+            // 资源随版本号固定，浏览器可以长期缓存
+            .insert_header((header::CACHE_CONTROL, "public, max-age=604800"))
+            .body(content))
     }
 }
 
 fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(curriculum_board::hello)
-        .service(curriculum_board::get_course_groups_hash)
-        .service(curriculum_board::refresh_course_groups_cache)
-        .service(curriculum_board::get_course_groups)
-        .service(curriculum_board::get_course_group)
-        .service(curriculum_board::add_course)
-        .service(curriculum_board::get_course)
-        .service(curriculum_board::add_review)
-        .service(curriculum_board::modify_review)
-        .service(curriculum_board::vote_for_review)
-        .service(curriculum_board::get_reviews)
-        .service(curriculum_board::get_random_reviews)
-        .service(r#static::cedict)
-        .service(openapi::get_openapi);
+    // 解析请求失败和未匹配到路由时，也返回带错误码的 JSON
+    cfg.app_data(web::JsonConfig::default().error_handler(|e, _| error_handler::extractor_error(e)))
+        .app_data(web::QueryConfig::default().error_handler(|e, _| error_handler::extractor_error(e)))
+        .app_data(web::PathConfig::default().error_handler(|e, _| error_handler::extractor_error(e)))
+        .default_service(web::to(error_handler::route_not_found));
+    macro_rules! register {
+        ($($module:ident::$handler:ident),* $(,)?) => {
+            $(cfg.service($module::$handler);)*
+        };
+    }
+    api_services!(register);
+    cfg.service(openapi::get_openapi);
 }
 
 
@@ -98,16 +323,108 @@ fn config(cfg: &mut web::ServiceConfig) {
 async fn main() -> std::io::Result<()> {
     // 初始化 dotenv
     dotenv().ok();
-    let db: DatabaseConnection = Database::connect(env::var(constant::ENV_DB_URL).unwrap()).await.unwrap();
-    Migrator::up(&db, None).await.unwrap();
-    HttpServer::new(move || {
+    // 读取并校验配置，有误时拒绝启动
+    let settings = Settings::load().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    // 以 JSON 格式输出日志，每行一条
+    tracing_subscriber::fmt()
+        .json()
+        .flatten_event(true)
+        .with_current_span(false)
+        .with_env_filter(EnvFilter::new(&settings.log.level))
+        .init();
+    for locale in Locale::ALL {
+        let missing = locale.catalog().missing_errors();
+        if !missing.is_empty() {
+            tracing::warn!(locale = locale.tag(), ?missing, "missing translations of error messages");
+        }
+    }
+    error_handler::configure_error_details(settings.environment == Environment::Development);
+    auth::configure_user_cache(settings.cache.user);
+    Userextra::configure_cache(
+        settings.cache.user_extra.capacity,
+        settings.cache.user_extra.time_to_live(),
+    );
+    Userextra::observe_cache(|hit| metrics::observe_cache("user_extra", hit));
+
+    let mut connect_options = ConnectOptions::new(settings.database.url.clone());
+    connect_options
+        .max_connections(settings.database.max_connections)
+        .min_connections(settings.database.min_connections);
+    let mut db: DatabaseConnection = Database::connect(connect_options).await.map_err(|e| {
+        io::Error::other(format!("Unable to connect to the database. Error: {}", e))
+    })?;
+    db.set_metric_callback(metrics::observe_db_query);
+    if settings.database.auto_migrate {
+        Migrator::up(&db, None)
+            .await
+            .map_err(|e| io::Error::other(format!("Unable to run migrations. Error: {}", e)))?;
+    }
+    // 在后台预热课程组缓存，完成前就绪检查不通过
+    actix_web::rt::spawn(curriculum_board::warm_course_group_cache(db.clone()));
+    let rate_limit_config = web::Data::new(RateLimitConfig::from_settings(&settings.rate_limit));
+    let achievement_rules = web::Data::new(
+        AchievementRules::load(settings.achievement_rules_file.as_deref())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+    );
+    let pseudonymizer = web::Data::new(Pseudonymizer::new(settings.pseudonym_secret.clone()));
+    let bind = settings.server.bind.clone();
+    let metrics_bind = settings.server.metrics_bind.clone();
+    let workers = settings.server.workers;
+    let shutdown_timeout = settings.server.shutdown_timeout_secs;
+    let docs_enabled = settings.docs.enabled;
+    let api_spec = web::Data::new(openapi::ApiSpec::new(Some(&settings)));
+    let pool = db.clone();
+    let settings = web::Data::new(settings);
+    let mut server = HttpServer::new(move || {
         App::new()
+            .wrap(middleware::from_fn(rate_limit))
             .wrap(middleware::Compress::default())
+            .wrap(middleware::from_fn(negotiate_locale))
+            .wrap(middleware::from_fn(log_request))
             .configure(config)
+            .configure(|cfg| {
+                if docs_enabled {
+                    cfg.service(openapi::docs).service(openapi::docs_asset);
+                }
+            })
             .app_data(web::Data::new(db.clone()))
+            .app_data(settings.clone())
+            .app_data(rate_limit_config.clone())
+            .app_data(achievement_rules.clone())
+            .app_data(pseudonymizer.clone())
+            .app_data(api_spec.clone())
+    });
+    if let Some(workers) = workers {
+        server = server.workers(workers);
+    }
+    // 指标只在单独的地址上提供，不经过 API 的中间件
+    let metrics_server = match metrics_bind {
+        Some(metrics_bind) => Some(
+            HttpServer::new(|| App::new().service(metrics::metrics))
+                .workers(1)
+                .shutdown_timeout(shutdown_timeout)
+                .bind(metrics_bind)?
+                .run(),
+        ),
+        None => None,
+    };
+    // 收到 SIGINT 或 SIGTERM 后停止接受新连接，等待正在处理的请求完成后再关闭连接池
+    let server = server
+        .shutdown_timeout(shutdown_timeout)
+        .bind(bind)?
+        .run();
+    futures_util::future::try_join(server, async {
+        match metrics_server {
+            Some(metrics_server) => metrics_server.await,
+            None => Ok(()),
+        }
     })
-        .bind(("0.0.0.0", 11451))?
-        .run()
+    .await?;
+    pool.close()
         .await
+        .map_err(|e| io::Error::other(format!("Unable to close the database pool. Error: {}", e)))
 }
 
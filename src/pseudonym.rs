@@ -0,0 +1,67 @@
+use entity::course::GetSingleCourse;
+use entity::review::GetReview;
+use sea_orm::prelude::Json;
+use sha3::{Digest, Sha3_256};
+use crate::api::auth::UserInfo;
+
+/// 为评论者生成课程内稳定的假名，避免通过 `reviewer_id` 关联同一用户在不同课程下的评论。
+///
+/// 假名由服务端密钥、课程 id 和用户 id 共同哈希得到：同一用户在同一课程下的假名不变，
+/// 在不同课程下互不相关。管理员仍可看到真实的 id。
+pub struct Pseudonymizer {
+    secret: String,
+}
+
+impl Pseudonymizer {
+    pub fn new(secret: String) -> Self {
+        Pseudonymizer { secret }
+    }
+
+    pub fn pseudonym(&self, course_id: i32, user_id: i32) -> String {
+        let mut hasher = Sha3_256::new();
+        hasher.update(self.secret.as_bytes());
+        hasher.update(course_id.to_be_bytes());
+        hasher.update(user_id.to_be_bytes());
+        let hash = base16ct::lower::encode_string(&hasher.finalize());
+        hash[..12].to_string()
+    }
+
+    /// 将历史记录中的修改者 `alter_by` 和原评论者 `original.reviewer_id` 替换为假名。
+    pub fn apply_to_history(&self, history: &mut Json, course_id: i32, viewer: &UserInfo) {
+        if viewer.is_admin {
+            return;
+        }
+        let Some(entries) = history.as_array_mut() else {
+            return;
+        };
+        for entry in entries {
+            if let Some(alter_by) = entry.get_mut("alter_by") {
+                if let Some(id) = alter_by.as_i64() {
+                    *alter_by = self.pseudonym(course_id, id as i32).into();
+                }
+            }
+            if let Some(reviewer_id) = entry.pointer_mut("/original/reviewer_id") {
+                if let Some(id) = reviewer_id.as_i64() {
+                    *reviewer_id = self.pseudonym(course_id, id as i32).into();
+                }
+            }
+        }
+    }
+
+    /// 为评论填充假名。非管理员看不到真实的 `reviewer_id`。
+    pub fn apply(&self, review: &mut GetReview, course_id: i32, viewer: &UserInfo) {
+        if let Some(reviewer_id) = review.reviewer_id {
+            review.reviewer_pseudonym = self.pseudonym(course_id, reviewer_id);
+            if !viewer.is_admin {
+                review.reviewer_id = None;
+            }
+        }
+        self.apply_to_history(&mut review.history, course_id, viewer);
+    }
+
+    pub fn apply_to_course(&self, course: &mut GetSingleCourse, viewer: &UserInfo) {
+        for review in &mut course.review_list {
+            self.apply(review, course.id, viewer);
+        }
+    }
+}
@@ -0,0 +1,434 @@
+use std::env;
+use std::fmt::Display;
+use std::fs::read_to_string;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
+use crate::api::rate_limit::RateLimit;
+use crate::constant;
+
+/// 服务器的全部配置。
+///
+/// 先读取 `CONFIG_FILE` 指定的 TOML 文件（默认为 `config.toml`，不存在时使用默认值），
+/// 再用环境变量覆盖其中的值，最后在启动时统一校验，一次报告所有错误。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub environment: Environment,
+    /// 成就规则文件，未指定时不启用自动授予
+    pub achievement_rules_file: Option<String>,
+    /// 生成评论者假名的密钥
+    pub pseudonym_secret: String,
+    pub server: ServerSettings,
+    pub database: DatabaseSettings,
+    pub auth: AuthSettings,
+    pub cache: CacheSettings,
+    pub rate_limit: RateLimitSettings,
+    pub log: LogSettings,
+    pub docs: DocsSettings,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Environment {
+    /// 不允许关闭身份验证，不向客户端返回内部错误的细节
+    #[default]
+    Production,
+    Development,
+}
+
+impl FromStr for Environment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "production" => Ok(Environment::Production),
+            "development" => Ok(Environment::Development),
+            _ => Err("expected `production` or `development`".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    pub bind: String,
+    /// 提供 `/metrics` 的地址，与 `bind` 分开，只应在内网或本机可达。未指定时不提供指标
+    pub metrics_bind: Option<String>,
+    /// 工作线程数，未指定时与 CPU 核数相同
+    pub workers: Option<usize>,
+    pub static_dir: String,
+    /// 收到退出信号后，等待正在处理的请求完成的最长时间
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        ServerSettings {
+            bind: "0.0.0.0:11451".to_string(),
+            metrics_bind: None,
+            workers: None,
+            static_dir: "static".to_string(),
+            shutdown_timeout_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseSettings {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    /// 启动时自动执行迁移。关闭后需单独运行迁移，未完成前服务不会就绪
+    pub auto_migrate: bool,
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        DatabaseSettings {
+            url: String::new(),
+            max_connections: 10,
+            min_connections: 1,
+            auto_migrate: true,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMode {
+    /// 向 `auth.url` 验证请求携带的 Authorization 头
+    #[default]
+    Remote,
+    /// 不验证身份，所有请求都视为 `auth.dev_user_id` 这位管理员发出。仅能在 development 环境使用
+    Disabled,
+}
+
+impl FromStr for AuthMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "remote" => Ok(AuthMode::Remote),
+            "disabled" => Ok(AuthMode::Disabled),
+            _ => Err("expected `remote` or `disabled`".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthSettings {
+    pub mode: AuthMode,
+    /// 返回当前用户信息的接口
+    pub url: Option<String>,
+    pub dev_user_id: i32,
+    /// 就绪检查是否包括认证服务的可达性
+    pub readiness_check: bool,
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        AuthSettings {
+            mode: AuthMode::Remote,
+            url: None,
+            dev_user_id: 1,
+            readiness_check: false,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CacheLimit {
+    pub capacity: u64,
+    /// 缓存项的存活时间，未指定时不过期
+    pub ttl_secs: Option<u64>,
+}
+
+impl CacheLimit {
+    pub fn time_to_live(&self) -> Option<Duration> {
+        self.ttl_secs.map(Duration::from_secs)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheSettings {
+    /// Authorization 头到用户信息的缓存
+    pub user: CacheLimit,
+    /// 用户成就等附加信息的缓存
+    pub user_extra: CacheLimit,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        CacheSettings {
+            user: CacheLimit {
+                capacity: 10000,
+                ttl_secs: None,
+            },
+            user_extra: CacheLimit {
+                capacity: 10000,
+                ttl_secs: Some(60 * 5),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitSettings {
+    pub add_review: RateLimit,
+    pub modify_review: RateLimit,
+    pub vote_for_review: RateLimit,
+    /// 导出个人数据需要遍历评论，开销较大
+    pub export_personal_data: RateLimit,
+    pub erase_personal_data: RateLimit,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        let limit = |max_requests, seconds| RateLimit {
+            max_requests,
+            period: Duration::from_secs(seconds),
+        };
+        RateLimitSettings {
+            add_review: limit(5, 60),
+            modify_review: limit(10, 60),
+            vote_for_review: limit(30, 60),
+            export_personal_data: limit(5, 3600),
+            erase_personal_data: limit(3, 3600),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogSettings {
+    /// 日志级别，支持 `info,sqlx=warn` 这样按模块指定的写法
+    pub level: String,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        LogSettings {
+            level: "info".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DocsSettings {
+    /// 是否在 `/docs` 提供 Swagger UI。`/openapi.json` 不受影响
+    pub enabled: bool,
+    /// 写入 OpenAPI 文档的服务器地址，未指定时 Swagger UI 向当前地址发送请求
+    pub servers: Vec<String>,
+}
+
+impl Default for DocsSettings {
+    fn default() -> Self {
+        DocsSettings {
+            enabled: true,
+            servers: vec![],
+        }
+    }
+}
+
+/// 若环境变量存在，解析后覆盖 `target`
+fn override_with<T>(
+    target: &mut T,
+    name: &str,
+    env: &dyn Fn(&str) -> Option<String>,
+    errors: &mut Vec<String>,
+) where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Some(value) = env(name) {
+        match value.parse() {
+            Ok(value) => *target = value,
+            Err(e) => errors.push(format!("{}: invalid value `{}`, {}", name, value, e)),
+        }
+    }
+}
+
+fn override_option_with<T>(
+    target: &mut Option<T>,
+    name: &str,
+    env: &dyn Fn(&str) -> Option<String>,
+    errors: &mut Vec<String>,
+) where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Some(value) = env(name) {
+        match value.parse() {
+            Ok(value) => *target = Some(value),
+            Err(e) => errors.push(format!("{}: invalid value `{}`, {}", name, value, e)),
+        }
+    }
+}
+
+impl Settings {
+    /// 从配置文件和环境变量读取并校验配置
+    pub fn load() -> Result<Self, String> {
+        let content = match env::var(constant::ENV_CONFIG_FILE) {
+            Ok(path) => Some(
+                read_to_string(&path)
+                    .map_err(|e| format!("Unable to read the config file `{}`. Error: {}", path, e))?,
+            ),
+            // 未指定配置文件时，默认的配置文件可以不存在
+            Err(_) => read_to_string("config.toml").ok(),
+        };
+        Settings::load_from(content.as_deref(), &|name| env::var(name).ok())
+    }
+
+    /// 由配置文件内容和环境变量读取并校验配置
+    pub fn load_from(
+        content: Option<&str>,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Self, String> {
+        let mut settings = match content {
+            Some(content) => toml::from_str(content)
+                .map_err(|e| format!("Unable to parse the config file. Error: {}", e))?,
+            None => Settings::default(),
+        };
+        let mut errors = vec![];
+        settings.apply_env(env, &mut errors);
+        settings.validate(&mut errors);
+        if errors.is_empty() {
+            Ok(settings)
+        } else {
+            Err(format!("Invalid settings:\n  {}", errors.join("\n  ")))
+        }
+    }
+
+    fn apply_env(&mut self, env: &dyn Fn(&str) -> Option<String>, errors: &mut Vec<String>) {
+        override_with(&mut self.environment, constant::ENV_ENVIRONMENT, env, errors);
+        override_option_with(&mut self.achievement_rules_file, constant::ENV_ACHIEVEMENT_RULES_FILE, env, errors);
+        override_with(&mut self.pseudonym_secret, constant::ENV_PSEUDONYM_SECRET, env, errors);
+        override_with(&mut self.server.bind, constant::ENV_BIND_ADDRESS, env, errors);
+        override_option_with(&mut self.server.metrics_bind, constant::ENV_METRICS_BIND_ADDRESS, env, errors);
+        override_option_with(&mut self.server.workers, constant::ENV_WORKERS, env, errors);
+        override_with(&mut self.server.static_dir, constant::ENV_STATIC_DIR, env, errors);
+        override_with(&mut self.server.shutdown_timeout_secs, constant::ENV_SHUTDOWN_TIMEOUT, env, errors);
+        override_with(&mut self.database.url, constant::ENV_DB_URL, env, errors);
+        override_with(&mut self.database.max_connections, constant::ENV_DB_MAX_CONNECTIONS, env, errors);
+        override_with(&mut self.database.min_connections, constant::ENV_DB_MIN_CONNECTIONS, env, errors);
+        override_with(&mut self.database.auto_migrate, constant::ENV_AUTO_MIGRATE, env, errors);
+        override_with(&mut self.auth.mode, constant::ENV_AUTH_MODE, env, errors);
+        override_option_with(&mut self.auth.url, constant::ENV_USER_VERIFICATION_ADDRESS, env, errors);
+        override_with(&mut self.auth.dev_user_id, constant::ENV_DEV_USER_ID, env, errors);
+        override_with(&mut self.auth.readiness_check, constant::ENV_AUTH_READINESS_CHECK, env, errors);
+        override_with(&mut self.cache.user.capacity, constant::ENV_USER_CACHE_CAPACITY, env, errors);
+        override_option_with(&mut self.cache.user.ttl_secs, constant::ENV_USER_CACHE_TTL, env, errors);
+        override_with(&mut self.cache.user_extra.capacity, constant::ENV_USER_EXTRA_CACHE_CAPACITY, env, errors);
+        override_option_with(&mut self.cache.user_extra.ttl_secs, constant::ENV_USER_EXTRA_CACHE_TTL, env, errors);
+        override_with(&mut self.rate_limit.add_review, constant::ENV_RATE_LIMIT_ADD_REVIEW, env, errors);
+        override_with(&mut self.rate_limit.modify_review, constant::ENV_RATE_LIMIT_MODIFY_REVIEW, env, errors);
+        override_with(&mut self.rate_limit.vote_for_review, constant::ENV_RATE_LIMIT_VOTE_FOR_REVIEW, env, errors);
+        override_with(&mut self.rate_limit.export_personal_data, constant::ENV_RATE_LIMIT_EXPORT_PERSONAL_DATA, env, errors);
+        override_with(&mut self.rate_limit.erase_personal_data, constant::ENV_RATE_LIMIT_ERASE_PERSONAL_DATA, env, errors);
+        override_with(&mut self.log.level, constant::ENV_LOG_LEVEL, env, errors);
+        override_with(&mut self.docs.enabled, constant::ENV_DOCS_ENABLED, env, errors);
+        // 多个地址以逗号分隔
+        if let Some(servers) = env(constant::ENV_DOCS_SERVERS) {
+            self.docs.servers = servers
+                .split(',')
+                .map(str::trim)
+                .filter(|server| !server.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+    }
+
+    fn validate(&self, errors: &mut Vec<String>) {
+        // 旧版示例配置中的占位符
+        if self.pseudonym_secret.is_empty() || self.pseudonym_secret == "change me" {
+            errors.push(format!(
+                "pseudonym_secret ({}) must be set to a random secret to generate reviewer pseudonyms.",
+                constant::ENV_PSEUDONYM_SECRET
+            ));
+        }
+        if self.server.bind.parse::<SocketAddr>().is_err() {
+            errors.push(format!(
+                "server.bind ({}) must be an address like `0.0.0.0:11451`, got `{}`.",
+                constant::ENV_BIND_ADDRESS,
+                self.server.bind
+            ));
+        }
+        if let Some(metrics_bind) = &self.server.metrics_bind {
+            if metrics_bind.parse::<SocketAddr>().is_err() {
+                errors.push(format!(
+                    "server.metrics_bind ({}) must be an address like `127.0.0.1:11452`, got `{}`.",
+                    constant::ENV_METRICS_BIND_ADDRESS,
+                    metrics_bind
+                ));
+            } else if *metrics_bind == self.server.bind {
+                errors.push(format!(
+                    "server.metrics_bind ({}) must differ from server.bind, metrics are not served on the public address.",
+                    constant::ENV_METRICS_BIND_ADDRESS
+                ));
+            }
+        }
+        if self.server.workers == Some(0) {
+            errors.push(format!("server.workers ({}) must be at least 1.", constant::ENV_WORKERS));
+        }
+        if self.database.url.is_empty() {
+            errors.push(format!("database.url ({}) must be set.", constant::ENV_DB_URL));
+        }
+        if self.database.max_connections == 0 {
+            errors.push(format!(
+                "database.max_connections ({}) must be at least 1.",
+                constant::ENV_DB_MAX_CONNECTIONS
+            ));
+        }
+        if self.database.min_connections > self.database.max_connections {
+            errors.push(format!(
+                "database.min_connections ({}) must not exceed database.max_connections.",
+                constant::ENV_DB_MIN_CONNECTIONS
+            ));
+        }
+        if self.auth.mode == AuthMode::Disabled && self.environment != Environment::Development {
+            errors.push(format!(
+                "auth.mode ({}) can only be `disabled` when environment ({}) is `development`.",
+                constant::ENV_AUTH_MODE,
+                constant::ENV_ENVIRONMENT
+            ));
+        }
+        if self.auth.mode == AuthMode::Remote && self.auth.url.as_deref().is_none_or(str::is_empty) {
+            errors.push(format!(
+                "auth.url ({}) must be set when auth.mode is `remote`.",
+                constant::ENV_USER_VERIFICATION_ADDRESS
+            ));
+        }
+        for (name, env_name, limit) in [
+            ("cache.user", constant::ENV_USER_CACHE_CAPACITY, &self.cache.user),
+            ("cache.user_extra", constant::ENV_USER_EXTRA_CACHE_CAPACITY, &self.cache.user_extra),
+        ] {
+            if limit.capacity == 0 {
+                errors.push(format!("{}.capacity ({}) must be at least 1.", name, env_name));
+            }
+            if limit.ttl_secs == Some(0) {
+                errors.push(format!("{}.ttl_secs must be at least 1.", name));
+            }
+        }
+        if let Err(e) = EnvFilter::try_new(&self.log.level) {
+            errors.push(format!("log.level ({}) is invalid: {}", constant::ENV_LOG_LEVEL, e));
+        }
+        for server in &self.docs.servers {
+            if !server.starts_with("http://") && !server.starts_with("https://") && !server.starts_with('/') {
+                errors.push(format!(
+                    "docs.servers ({}) must be URLs like `https://example.com/api` or paths like `/api`, got `{}`.",
+                    constant::ENV_DOCS_SERVERS,
+                    server
+                ));
+            }
+        }
+    }
+}